@@ -0,0 +1,38 @@
+//! Compares running day09 part 2, the longest running 2019 program in this
+//! repository, with and without the instruction cache.
+//!
+//! Run with `cargo bench -p intcode --bench cache`.
+
+use intcode::{IntCodeComputer, Program, Status};
+use std::time::Instant;
+
+const DAY09: &str = include_str!("../../../years/aoc2019/src/day09/input.txt");
+
+fn day09(program: &Program, cache: bool) {
+    let mut computer = IntCodeComputer::new(program);
+    computer.set_instruction_cache(cache);
+    computer.provide_input(2);
+    while computer.run().unwrap() != Status::Halted {}
+}
+
+fn main() {
+    let program: Program = DAY09.parse().unwrap();
+
+    let mut results = Vec::new();
+    for &cache in &[false, true] {
+        // warm up
+        day09(&program, cache);
+
+        let start = Instant::now();
+        for _ in 0..10 {
+            day09(&program, cache);
+        }
+        let elapsed = start.elapsed().as_secs_f64() * 100.0;
+        results.push(elapsed);
+
+        let name = if cache { "cached" } else { "uncached" };
+        println!("day09 part 2 {:<12} {:>10.3} ms", name, elapsed);
+    }
+
+    println!("speed-up: {:.2}x", results[0] / results[1]);
+}
@@ -0,0 +1,71 @@
+//! Compares the memory backends on the 2019 puzzle inputs in this repository.
+//!
+//! Run with `cargo bench -p intcode --bench memory`.
+
+use intcode::{
+    IntCodeComputer, MemoryBackend, MemoryValue, PagedMemory, Program, SparseMemory, Status,
+};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+const DAY09: &str = include_str!("../../../years/aoc2019/src/day09/input.txt");
+const DAY11: &str = include_str!("../../../years/aoc2019/src/day11/input.txt");
+
+/// Runs day09 in sensor boost mode.
+fn day09<B: MemoryBackend<Word = MemoryValue>>(program: &Program) {
+    let mut computer = IntCodeComputer::<B>::with_backend(program);
+    computer.provide_input(2);
+    while computer.run().unwrap() != Status::Halted {}
+}
+
+/// Runs day11's painting robot, starting on a white panel.
+fn day11<B: MemoryBackend<Word = MemoryValue>>(program: &Program) {
+    let mut computer = IntCodeComputer::<B>::with_backend(program);
+    let mut painted = HashMap::new();
+    painted.insert((0, 0), 1);
+    let (mut position, mut direction) = ((0, 0), (0, -1));
+    loop {
+        match computer.run().unwrap() {
+            Status::NeedsInput => {
+                computer.provide_input(painted.get(&position).copied().unwrap_or(0));
+            }
+            Status::Output(color) => {
+                painted.insert(position, color);
+                direction = match computer.run().unwrap() {
+                    Status::Output(0) => (direction.1, -direction.0),
+                    _ => (-direction.1, direction.0),
+                };
+                position = (position.0 + direction.0, position.1 + direction.1);
+            }
+            Status::Halted => break,
+        }
+    }
+}
+
+fn bench(name: &str, iterations: u32, mut f: impl FnMut()) {
+    // warm up
+    f();
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    let elapsed = start.elapsed() / iterations;
+    println!("{:<24} {:>10.3} ms", name, duration_ms(elapsed));
+}
+
+fn duration_ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn main() {
+    let day09_program: Program = DAY09.parse().unwrap();
+    let day11_program: Program = DAY11.parse().unwrap();
+
+    bench("day09 paged", 5, || day09::<PagedMemory>(&day09_program));
+    bench("day09 sparse", 5, || day09::<SparseMemory>(&day09_program));
+    bench("day11 paged", 20, || day11::<PagedMemory>(&day11_program));
+    bench("day11 sparse", 20, || day11::<SparseMemory>(&day11_program));
+}
@@ -0,0 +1,225 @@
+use crate::{IntCodeComputer, IntcodeError, MemoryValue, Status};
+use anyhow::{bail, Context};
+use std::{
+    fmt::{self, Display, Formatter},
+    io::{BufRead, Write},
+};
+
+/// A piece of output from a program that communicates in ASCII.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub enum AsciiOutput {
+    /// A run of consecutive ASCII characters.
+    Text(String),
+    /// A value outside the ASCII range, such as a puzzle answer.
+    Value(MemoryValue),
+}
+
+impl Display for AsciiOutput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AsciiOutput::Text(text) => write!(f, "{}", text),
+            AsciiOutput::Value(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// Decodes output values into text, keeping values outside the ASCII range as
+/// numbers.
+pub fn decode_ascii(values: &[MemoryValue]) -> Vec<AsciiOutput> {
+    let mut output = Vec::new();
+    for &value in values {
+        match (value, output.last_mut()) {
+            (0..=127, Some(AsciiOutput::Text(text))) => text.push(value as u8 as char),
+            (0..=127, _) => output.push(AsciiOutput::Text((value as u8 as char).to_string())),
+            _ => output.push(AsciiOutput::Value(value)),
+        }
+    }
+    output
+}
+
+/// Why an [`AsciiComputer`] stopped running.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum AsciiStatus {
+    /// The program is waiting for a line of input.
+    NeedsInput,
+    Halted,
+}
+
+/// Runs an [`IntCodeComputer`] that reads and writes lines of ASCII text.
+pub struct AsciiComputer {
+    computer: IntCodeComputer,
+    output: Vec<MemoryValue>,
+}
+
+impl AsciiComputer {
+    pub fn new(computer: IntCodeComputer) -> Self {
+        AsciiComputer {
+            computer,
+            output: Vec::new(),
+        }
+    }
+
+    pub fn computer(&self) -> &IntCodeComputer {
+        &self.computer
+    }
+
+    pub fn computer_mut(&mut self) -> &mut IntCodeComputer {
+        &mut self.computer
+    }
+
+    pub fn into_inner(self) -> IntCodeComputer {
+        self.computer
+    }
+
+    /// Queues a line of input, followed by a newline.
+    pub fn send_line(&mut self, line: &str) -> anyhow::Result<()> {
+        if let Some(c) = line.chars().find(|c| !c.is_ascii()) {
+            bail!("not an ASCII character: {:?}", c);
+        }
+
+        for byte in line.bytes().chain(Some(b'\n')) {
+            self.computer.provide_input(byte.into());
+        }
+        Ok(())
+    }
+
+    /// Runs the program until it halts or needs more input than has been sent.
+    pub fn run(&mut self) -> Result<AsciiStatus, IntcodeError> {
+        loop {
+            match self.computer.run()? {
+                Status::Output(value) => self.output.push(value),
+                Status::NeedsInput => return Ok(AsciiStatus::NeedsInput),
+                Status::Halted => return Ok(AsciiStatus::Halted),
+            }
+        }
+    }
+
+    /// Takes the output written since the last call, decoded into text.
+    pub fn take_output(&mut self) -> Vec<AsciiOutput> {
+        let output = decode_ascii(&self.output);
+        self.output.clear();
+        output
+    }
+
+    /// Plays the program in a terminal, sending each line read from `input`
+    /// to the program and writing its output to `output`. Values outside the
+    /// ASCII range are written on their own line. Stops when the program halts
+    /// or `input` runs out.
+    pub fn interact<R, W>(&mut self, input: R, mut output: W) -> anyhow::Result<AsciiStatus>
+    where
+        R: BufRead,
+        W: Write,
+    {
+        let mut lines = input.lines();
+        let mut line_start = true;
+        loop {
+            let status = self.run()?;
+            for item in self.take_output() {
+                match item {
+                    AsciiOutput::Text(text) => {
+                        write!(output, "{}", text)?;
+                        line_start = text.ends_with('\n');
+                    }
+                    AsciiOutput::Value(value) => {
+                        if !line_start {
+                            writeln!(output)?;
+                        }
+                        writeln!(output, "{}", value)?;
+                        line_start = true;
+                    }
+                }
+            }
+            output.flush()?;
+
+            if status == AsciiStatus::Halted {
+                return Ok(status);
+            }
+
+            // only read a line once the program asks for one
+            match lines.next() {
+                Some(line) => {
+                    let line = line.context("reading input")?;
+                    self.send_line(line.trim_end_matches('\r'))?;
+                }
+                None => return Ok(status),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Program;
+
+    /// Prompts for a line, echoes it back, then outputs a large value.
+    const ECHO: &str = "
+              OUT #62
+              OUT #10
+        loop: IN -> [c]
+              OUT [c]
+              EQ [c], #10 -> [nl]
+              JF [nl], #loop
+              OUT #1000
+              HLT
+        c:    DATA 0
+        nl:   DATA 0
+    ";
+
+    fn echo() -> AsciiComputer {
+        let program = Program::from(crate::assemble(ECHO).unwrap());
+        AsciiComputer::new(IntCodeComputer::new(&program))
+    }
+
+    #[test]
+    fn decode() {
+        assert_eq!(
+            decode_ascii(&[72, 105, 10, 1000, -1, 33]),
+            [
+                AsciiOutput::Text("Hi\n".to_string()),
+                AsciiOutput::Value(1000),
+                AsciiOutput::Value(-1),
+                AsciiOutput::Text("!".to_string()),
+            ]
+        );
+        assert!(decode_ascii(&[]).is_empty());
+    }
+
+    #[test]
+    fn lines() {
+        let mut computer = echo();
+        assert_eq!(computer.run().unwrap(), AsciiStatus::NeedsInput);
+        assert_eq!(
+            computer.take_output(),
+            [AsciiOutput::Text(">\n".to_string())]
+        );
+
+        assert!(computer.send_line("héllo").is_err());
+        computer.send_line("hello").unwrap();
+        assert_eq!(computer.run().unwrap(), AsciiStatus::Halted);
+        assert_eq!(
+            computer.take_output(),
+            [
+                AsciiOutput::Text("hello\n".to_string()),
+                AsciiOutput::Value(1000)
+            ]
+        );
+        assert!(computer.take_output().is_empty());
+    }
+
+    #[test]
+    fn interact() {
+        // input after the program halts is left unread
+        let mut input = &b"hello\r\nextra\n"[..];
+        let mut output = Vec::new();
+        let status = echo().interact(&mut input, &mut output);
+        assert_eq!(status.unwrap(), AsciiStatus::Halted);
+        assert_eq!(String::from_utf8(output).unwrap(), ">\nhello\n1000\n");
+        assert_eq!(input, b"extra\n");
+
+        let mut output = Vec::new();
+        let status = echo().interact(&b""[..], &mut output);
+        assert_eq!(status.unwrap(), AsciiStatus::NeedsInput);
+        assert_eq!(String::from_utf8(output).unwrap(), ">\n");
+    }
+}
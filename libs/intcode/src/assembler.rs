@@ -0,0 +1,335 @@
+use crate::{Instruction, MemoryValue, Mode, Opcode};
+use anyhow::{bail, Context};
+use std::collections::HashMap;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Value {
+    Literal(MemoryValue),
+    Label(String, MemoryValue),
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Statement {
+    Instruction(Opcode, Vec<(Mode, Value)>),
+    Data(Vec<Value>),
+}
+
+impl Statement {
+    fn len(&self) -> usize {
+        match self {
+            Statement::Instruction(_, operands) => operands.len() + 1,
+            Statement::Data(values) => values.len(),
+        }
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_value(s: &str) -> anyhow::Result<Value> {
+    let s = s.trim();
+    if s.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+        let value = s
+            .parse()
+            .with_context(|| format!("invalid number: {}", s))?;
+        return Ok(Value::Literal(value));
+    }
+
+    let (label, offset) = match s.find(&['+', '-'][..]) {
+        Some(index) => {
+            let offset = s[index..]
+                .trim_start_matches('+')
+                .parse()
+                .with_context(|| format!("invalid offset: {}", s))?;
+            (s[..index].trim_end(), offset)
+        }
+        None => (s, 0),
+    };
+
+    if !is_identifier(label) {
+        bail!("invalid label: {}", label);
+    }
+
+    Ok(Value::Label(label.to_string(), offset))
+}
+
+fn parse_operand(s: &str) -> anyhow::Result<(Mode, Value)> {
+    let s = s.trim();
+    if let Some(value) = s.strip_prefix('#') {
+        return Ok((Mode::Immediate, parse_value(value)?));
+    }
+
+    let inner = match s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        Some(inner) => inner.trim(),
+        None => bail!("operand must be #value, [address] or [rb+offset]: {}", s),
+    };
+
+    // labels can start with "rb" too, like [rbuf]
+    let relative = inner.strip_prefix("rb").filter(|offset| {
+        offset.is_empty() || offset.starts_with(|c: char| c.is_whitespace() || c == '+' || c == '-')
+    });
+    match relative {
+        Some("") => Ok((Mode::Relative, Value::Literal(0))),
+        Some(offset) => match offset.trim_start().strip_prefix('+') {
+            Some(offset) => Ok((Mode::Relative, parse_value(offset)?)),
+            None => Ok((Mode::Relative, parse_value(offset)?)),
+        },
+        None => Ok((Mode::Position, parse_value(inner)?)),
+    }
+}
+
+fn split_list(s: &str) -> Vec<&str> {
+    if s.is_empty() {
+        Vec::new()
+    } else {
+        s.split(',').map(str::trim).collect()
+    }
+}
+
+fn parse_statement(s: &str) -> anyhow::Result<Statement> {
+    let (mnemonic, rest) = match s.find(char::is_whitespace) {
+        Some(index) => (&s[..index], s[index..].trim()),
+        None => (s, ""),
+    };
+    let mnemonic = mnemonic.to_ascii_uppercase();
+    if mnemonic == "DATA" {
+        let values = split_list(rest)
+            .into_iter()
+            .map(parse_value)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if values.is_empty() {
+            bail!("DATA needs at least one value");
+        }
+
+        return Ok(Statement::Data(values));
+    }
+
+    let opcode = Opcode::from_mnemonic(&mnemonic)
+        .with_context(|| format!("unknown mnemonic: {}", mnemonic))?;
+    let (inputs, output) = match rest.find("->") {
+        Some(index) => (rest[..index].trim(), Some(rest[index + 2..].trim())),
+        None => (rest, None),
+    };
+
+    let mut operands = split_list(inputs)
+        .into_iter()
+        .map(parse_operand)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    match (output, opcode.output_parameter()) {
+        (Some(output), Some(index)) if index <= operands.len() => {
+            operands.insert(index, parse_operand(output)?);
+        }
+        (Some(_), _) => bail!("{} has no output operand here", opcode),
+        (None, _) => {}
+    }
+
+    if operands.len() != opcode.arity() {
+        bail!(
+            "{} takes {} operands, got {}",
+            opcode,
+            opcode.arity(),
+            operands.len()
+        );
+    }
+
+    Ok(Statement::Instruction(opcode, operands))
+}
+
+/// Assembles a program from source text into memory.
+///
+/// Each line holds an optional label, an optional statement and an optional
+/// comment starting with `;`. Statements are either instructions written with
+/// the mnemonics produced by [`disassemble`](crate::disassemble), like
+/// `ADD #3, [rb-1] -> [total]`, or `DATA` directives listing raw values.
+/// Operands are written as `#value` for immediate mode, `[address]` for
+/// position mode and `[rb+offset]` for relative mode. Values may be numbers,
+/// labels, or labels with an offset like `end+2`.
+///
+/// A label is an identifier followed by `:`, and refers to the address of the
+/// next statement. A number followed by `:`, like the addresses in a
+/// disassembly listing, must match the address of the next statement.
+pub fn assemble(source: &str) -> anyhow::Result<Vec<MemoryValue>> {
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
+    let mut addr = 0;
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let mut line = match line.find(';') {
+            Some(index) => &line[..index],
+            None => line,
+        }
+        .trim();
+
+        while let Some(index) = line.find(':') {
+            let label = line[..index].trim();
+            if let Ok(expected) = label.parse::<usize>() {
+                if expected != addr {
+                    bail!(
+                        "line {}: listed at address {} but assembled at {}",
+                        line_number,
+                        expected,
+                        addr
+                    );
+                }
+            } else if is_identifier(label) {
+                if labels.insert(label.to_string(), addr).is_some() {
+                    bail!("line {}: duplicate label: {}", line_number, label);
+                }
+            } else {
+                break;
+            }
+
+            line = line[index + 1..].trim();
+        }
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let statement = parse_statement(line).with_context(|| format!("line {}", line_number))?;
+        addr += statement.len();
+        statements.push((line_number, statement));
+    }
+
+    let resolve = |value: &Value, line_number: usize| -> anyhow::Result<MemoryValue> {
+        match value {
+            Value::Literal(value) => Ok(*value),
+            Value::Label(label, offset) => match labels.get(label) {
+                Some(&addr) => Ok(addr as MemoryValue + offset),
+                None => bail!("line {}: unknown label: {}", line_number, label),
+            },
+        }
+    };
+
+    let mut memory = Vec::with_capacity(addr);
+    for (line_number, statement) in statements {
+        match statement {
+            Statement::Instruction(opcode, operands) => {
+                let mut modes = [Mode::Position; 3];
+                for (mode, (operand_mode, _)) in modes.iter_mut().zip(&operands) {
+                    *mode = *operand_mode;
+                }
+
+                memory.push(Instruction::new(opcode, modes).encode());
+                for (_, value) in &operands {
+                    memory.push(resolve(value, line_number)?);
+                }
+            }
+            Statement::Data(values) => {
+                for value in &values {
+                    memory.push(resolve(value, line_number)?);
+                }
+            }
+        }
+    }
+
+    Ok(memory)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disassemble;
+
+    #[test]
+    fn instructions() {
+        let memory = assemble(
+            "
+            ADD #3, #5 -> [rb+104]
+            IN -> [100]
+            OUT [101]
+            MUL [15], [16] -> [17]
+            jt [7], #0
+            JF [rb-2], #12
+            ARB #-7
+            IN -> [rb]
+            HLT
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            memory,
+            [
+                21101, 3, 5, 104, 3, 100, 4, 101, 2, 15, 16, 17, 1005, 7, 0, 1206, -2, 12, 109, -7,
+                203, 0, 99
+            ]
+        );
+    }
+
+    #[test]
+    fn labels_and_data() {
+        let memory = assemble(
+            "
+            ; counts down from 3
+            start:  OUT [counter]
+                    ADD [counter], #-1 -> [counter]
+                    JT [counter], #start
+            end:    HLT
+            counter: DATA 3
+                    DATA end, counter+1, -4
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            memory,
+            [4, 10, 1001, 10, -1, 10, 1005, 10, 0, 99, 3, 9, 11, -4]
+        );
+    }
+
+    #[test]
+    fn labels_starting_with_rb() {
+        let memory = assemble(
+            "
+                  OUT [rbuf]
+                  ARB [rb + rbuf]
+            uf:   DATA 0
+            rbuf: DATA 1
+            ",
+        )
+        .unwrap();
+        assert_eq!(memory, [4, 5, 209, 5, 0, 1]);
+    }
+
+    #[test]
+    fn output_operand_without_arrow() {
+        assert_eq!(
+            assemble("ADD #1, #2, [0]").unwrap(),
+            assemble("ADD #1, #2 -> [0]").unwrap()
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let memory = vec![
+            3, 8, 1005, 8, 319, 1106, 0, 11, 0, 0, 0, 104, 1, 104, 0, 3, 8, 1002, 8, -1, 10, 1001,
+            10, 1, 10, 4, 10, 1008, 8, 0, 10, 4, 10, 1002, 8, 1, 29, 1, 1104, 7, 10, 3, 8, 1002, 8,
+            -1, 10, 1001, 10, 1, 10, 4, 10, 1008, 8, 0, 10, 4, 10, 99, 42, -3, 209, 5, 21202, -1,
+            7, -5, 99999,
+        ];
+        let listing = disassemble(&memory)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(assemble(&listing).unwrap(), memory);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(assemble("NOP").is_err());
+        assert!(assemble("ADD #1, #2").is_err());
+        assert!(assemble("OUT 5").is_err());
+        assert!(assemble("OUT #5 -> [1]").is_err());
+        assert!(assemble("JT #1, #missing").is_err());
+        assert!(assemble("a: HLT\na: HLT").is_err());
+        assert!(assemble("0001: HLT").is_err());
+        assert!(assemble("DATA").is_err());
+        assert!(assemble("DATA 1x").is_err());
+    }
+}
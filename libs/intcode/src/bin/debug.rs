@@ -0,0 +1,219 @@
+use anyhow::{bail, Context};
+use intcode::{
+    disassemble, load_program_file, Debugger, Event, IntCodeComputer, MemoryValue, Opcode, Program,
+    Status,
+};
+use std::io::{BufRead, Write};
+
+const HELP: &str = "\
+commands:
+  s, step [n]          execute n instructions, ignoring breakpoints
+  c, continue          run until a breakpoint, watchpoint, halt or input
+  bs, back [n]         undo the last n instructions executed
+  lw, last-write <addr>
+                       show the last instruction that wrote to an address
+  b, break <addr>      toggle a breakpoint on an address
+  bo <mnemonic>        toggle a breakpoint on an opcode, like bo OUT
+  w, watch <addr>      toggle a watchpoint on writes to an address
+  i, input <values>    queue input values
+  r, regs              show the instruction pointer and relative base
+  l, list [addr] [n]   disassemble n instructions from addr (default ip)
+  x <addr> [n]         show n memory values from addr
+  set <addr> <value>   write a value to memory
+  ip <addr>            move the instruction pointer
+  rb <value>           set the relative base
+  q, quit              exit the debugger";
+
+fn arg<T: std::str::FromStr>(args: &[&str], index: usize) -> anyhow::Result<Option<T>>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    match args.get(index) {
+        Some(arg) => Ok(Some(
+            arg.parse()
+                .with_context(|| format!("invalid argument: {}", arg))?,
+        )),
+        None => Ok(None),
+    }
+}
+
+fn required<T: std::str::FromStr>(args: &[&str], index: usize) -> anyhow::Result<T>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    arg(args, index)?.context("missing argument")
+}
+
+fn print_event(debugger: &Debugger, event: Event) {
+    match event {
+        Event::Stepped => {}
+        Event::Breakpoint(addr) => println!("breakpoint at {}", addr),
+        Event::Watchpoint { addr, old, new } => {
+            println!("[{}] changed from {} to {}", addr, old, new)
+        }
+        Event::Status(Status::Output(value)) => println!("> {}", value),
+        Event::Status(Status::NeedsInput) => println!("waiting for input"),
+        Event::Status(Status::Halted) => println!("halted"),
+    }
+
+    print_listing(debugger.computer(), debugger.computer().ip(), 1);
+}
+
+fn print_listing(computer: &IntCodeComputer, addr: usize, count: usize) {
+    // at most 4 cells per instruction
+    let memory: Vec<MemoryValue> = (addr..addr + count * 4)
+        .map(|addr| computer.get(addr))
+        .collect();
+    for mut line in disassemble(&memory).into_iter().take(count) {
+        line.addr += addr;
+        let marker = if line.addr == computer.ip() { '*' } else { ' ' };
+        println!("{}{}", marker, line);
+    }
+}
+
+fn toggle(added: bool, what: &str) {
+    println!("{} {}", if added { "added" } else { "removed" }, what);
+}
+
+fn execute(debugger: &mut Debugger, command: &str, args: &[&str]) -> anyhow::Result<bool> {
+    match command {
+        "s" | "step" => {
+            for _ in 0..arg(args, 0)?.unwrap_or(1) {
+                let event = debugger.step()?;
+                if event != Event::Stepped {
+                    print_event(debugger, event);
+                    return Ok(true);
+                }
+            }
+            print_event(debugger, Event::Stepped);
+        }
+        "c" | "continue" => loop {
+            let event = debugger.resume()?;
+            print_event(debugger, event);
+            if !matches!(event, Event::Status(Status::Output(_))) {
+                break;
+            }
+        },
+        "bs" | "back" => {
+            let count = arg(args, 0)?.unwrap_or(1);
+            for undone in 0..count {
+                if debugger.step_back().is_none() {
+                    println!("undid {} of {} instructions", undone, count);
+                    break;
+                }
+            }
+            print_listing(debugger.computer(), debugger.computer().ip(), 1);
+        }
+        "lw" | "last-write" => {
+            let addr = required(args, 0)?;
+            let entry = debugger
+                .journal()
+                .and_then(|journal| journal.last_write(addr));
+            match entry.and_then(|entry| Some((entry, entry.write_to(addr)?))) {
+                Some((entry, write)) => println!(
+                    "[{}] changed from {} to {} by the instruction at {} (step {})",
+                    addr, write.old, write.new, entry.ip, entry.step
+                ),
+                None => println!("no recorded writes to {}", addr),
+            }
+        }
+        "b" | "break" => {
+            let addr = required(args, 0)?;
+            let added = debugger.add_breakpoint(addr);
+            if !added {
+                debugger.remove_breakpoint(addr);
+            }
+            toggle(added, &format!("breakpoint at {}", addr));
+        }
+        "bo" => {
+            let mnemonic = required::<String>(args, 0)?.to_ascii_uppercase();
+            let opcode = Opcode::from_mnemonic(&mnemonic)
+                .with_context(|| format!("unknown mnemonic: {}", mnemonic))?;
+            let added = debugger.add_opcode_breakpoint(opcode);
+            if !added {
+                debugger.remove_opcode_breakpoint(opcode);
+            }
+            toggle(added, &format!("breakpoint on {}", opcode));
+        }
+        "w" | "watch" => {
+            let addr = required(args, 0)?;
+            let added = debugger.add_watchpoint(addr);
+            if !added {
+                debugger.remove_watchpoint(addr);
+            }
+            toggle(added, &format!("watchpoint on {}", addr));
+        }
+        "i" | "input" => {
+            for index in 0..args.len() {
+                let value = required(args, index)?;
+                debugger.computer_mut().provide_input(value);
+            }
+        }
+        "r" | "regs" => {
+            let computer = debugger.computer();
+            println!("ip = {}", computer.ip());
+            println!("relative base = {}", computer.relative_base());
+            println!("pending input = {:?}", computer.pending_input());
+        }
+        "l" | "list" => {
+            let addr = arg(args, 0)?.unwrap_or_else(|| debugger.computer().ip());
+            print_listing(debugger.computer(), addr, arg(args, 1)?.unwrap_or(10));
+        }
+        "x" => {
+            let addr: usize = required(args, 0)?;
+            for addr in addr..addr + arg(args, 1)?.unwrap_or(1) {
+                println!("[{:04}] = {}", addr, debugger.computer().get(addr));
+            }
+        }
+        "set" => {
+            let addr = required(args, 0)?;
+            debugger.computer_mut().set(addr, required(args, 1)?);
+        }
+        "ip" => debugger.computer_mut().set_ip(required(args, 0)?),
+        "rb" => debugger
+            .computer_mut()
+            .set_relative_base(required(args, 0)?),
+        "h" | "help" => println!("{}", HELP),
+        "q" | "quit" => return Ok(false),
+        _ => bail!("unknown command: {} (try help)", command),
+    }
+
+    Ok(true)
+}
+
+fn main() -> anyhow::Result<()> {
+    let path = std::env::args()
+        .nth(1)
+        .context("usage: intcode-debug <program>")?;
+    let memory = load_program_file(&path).with_context(|| format!("loading {}", path))?;
+
+    let mut debugger = Debugger::new(IntCodeComputer::new(&Program::from(memory)));
+    debugger.set_journal(true);
+    print_listing(debugger.computer(), 0, 1);
+
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("(intcode) ");
+        std::io::stdout().flush()?;
+
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => break,
+        };
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command,
+            None => continue,
+        };
+        let args: Vec<&str> = words.collect();
+
+        match execute(&mut debugger, command, &args) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(error) => println!("error: {:#}", error),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,19 @@
+use anyhow::Context;
+use intcode::{decompile, load_program};
+use std::io::Read;
+
+fn main() -> anyhow::Result<()> {
+    let input = match std::env::args().nth(1) {
+        Some(path) => std::fs::read(&path).with_context(|| format!("reading {}", path))?,
+        None => {
+            let mut input = Vec::new();
+            std::io::stdin().read_to_end(&mut input)?;
+            input
+        }
+    };
+
+    let memory = load_program(&input)?;
+    print!("{}", decompile(&memory));
+
+    Ok(())
+}
@@ -0,0 +1,272 @@
+use anyhow::{anyhow, bail, Context};
+use intcode::{
+    load_program_file, AsciiComputer, AsciiStatus, BinaryTraceWriter, IntCodeComputer,
+    JsonLinesWriter, Limits, MemoryValue, Profile, Program, Snapshot, Status, TraceSink, Tracer,
+    Validation,
+};
+use std::{
+    fs::File,
+    io::{BufRead, BufWriter},
+    time::Duration,
+};
+
+const USAGE: &str = "\
+usage: intcode-run <program> [options]
+       intcode-run --resume <snapshot> [options]
+
+options:
+  --input <values>         comma separated values to provide as input before
+                           reading input from stdin
+  --resume <path>          resume the machine saved in a snapshot instead of
+                           starting a program
+  --save <path>            if the machine stops before halting, such as when
+                           input runs out or a limit is hit, save a snapshot
+                           it can be resumed from
+  --ascii                  play the program in the terminal, sending each line
+                           typed as text and printing output as text
+  --trace <path>           write each executed instruction to a JSON Lines file
+  --binary-trace <path>    write each executed instruction to a binary trace
+  --profile                print how often each opcode, address and basic
+                           block ran once the machine stops
+  --profile-folded <path>  write how many instructions ran in each inferred
+                           stack of function calls, for flamegraph tools
+  --strict                 fault on malformed instructions instead of running
+                           them the way most Intcode machines do
+  --max-steps <n>          stop after executing n instructions
+  --timeout <seconds>      stop after running for the given number of seconds
+  --max-address <addr>     fault on accessing addresses at or past addr
+  --max-memory <cells>     fault when more than the given number of cells past
+                           the program are written to";
+
+struct Options {
+    program: Option<String>,
+    resume: Option<String>,
+    save: Option<String>,
+    input: Vec<MemoryValue>,
+    ascii: bool,
+    trace: Option<String>,
+    binary_trace: Option<String>,
+    profile: bool,
+    profile_folded: Option<String>,
+    strict: bool,
+    limits: Limits,
+}
+
+fn parse_args() -> anyhow::Result<Options> {
+    let mut args = std::env::args().skip(1);
+    let mut options = Options {
+        program: None,
+        resume: None,
+        save: None,
+        input: Vec::new(),
+        ascii: false,
+        trace: None,
+        binary_trace: None,
+        profile: false,
+        profile_folded: None,
+        strict: false,
+        limits: Limits::none(),
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .with_context(|| format!("missing value for {}", arg))
+        };
+        match arg.as_str() {
+            "--input" => {
+                options.input = value()?
+                    .split(',')
+                    .map(|s| s.trim().parse())
+                    .collect::<Result<_, _>>()?
+            }
+            "--resume" => options.resume = Some(value()?),
+            "--save" => options.save = Some(value()?),
+            "--ascii" => options.ascii = true,
+            "--trace" => options.trace = Some(value()?),
+            "--binary-trace" => options.binary_trace = Some(value()?),
+            "--profile" => options.profile = true,
+            "--profile-folded" => options.profile_folded = Some(value()?),
+            "--strict" => options.strict = true,
+            "--max-steps" => options.limits = options.limits.with_max_steps(value()?.parse()?),
+            "--timeout" => {
+                let timeout = Duration::from_secs_f64(value()?.parse()?);
+                options.limits = options.limits.with_timeout(timeout)
+            }
+            "--max-address" => options.limits = options.limits.with_max_address(value()?.parse()?),
+            "--max-memory" => options.limits = options.limits.with_max_allocated(value()?.parse()?),
+            "-h" | "--help" => bail!("{}", USAGE),
+            _ if arg.starts_with("--") => bail!("unknown option: {}\n\n{}", arg, USAGE),
+            _ => options.program = Some(arg),
+        }
+    }
+
+    match (&options.program, &options.resume) {
+        (None, None) => bail!("{}", USAGE),
+        (Some(_), Some(_)) => bail!("a program can't be given with --resume"),
+        _ => Ok(options),
+    }
+}
+
+/// Runs a machine to completion, giving it input from stdin once its pending
+/// input runs out. `run` provides the given input, if any, then runs the
+/// machine.
+fn run_to_end<F>(mut run: F) -> anyhow::Result<()>
+where
+    F: FnMut(Option<MemoryValue>) -> anyhow::Result<Status>,
+{
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut next_input = None;
+    loop {
+        match run(next_input.take())? {
+            Status::Output(value) => println!("> {}", value),
+            Status::NeedsInput => {
+                let line = lines.next().context("unexpected end of input")??;
+                next_input = Some(line.trim().parse()?);
+            }
+            Status::Halted => return Ok(()),
+        }
+    }
+}
+
+fn create(path: &str) -> anyhow::Result<BufWriter<File>> {
+    let file = File::create(path).with_context(|| format!("creating {}", path))?;
+    Ok(BufWriter::new(file))
+}
+
+/// Runs a machine to completion, recording each instruction it executes.
+fn run_traced<S: TraceSink>(
+    computer: IntCodeComputer,
+    sink: S,
+) -> (IntCodeComputer, S, anyhow::Result<bool>) {
+    let mut tracer = Tracer::new(computer, sink);
+    let result = run_to_end(|input| {
+        if let Some(value) = input {
+            tracer.computer_mut().provide_input(value);
+        }
+        Ok(tracer.run()?)
+    });
+    let (computer, sink) = tracer.into_parts();
+    (computer, sink, result.map(|_| true))
+}
+
+/// Runs the machine as the options say, returning it along with whether it
+/// halted.
+fn run(
+    mut computer: IntCodeComputer,
+    options: &Options,
+) -> (IntCodeComputer, anyhow::Result<bool>) {
+    let profile = options.profile || options.profile_folded.is_some();
+    let recorders = [
+        options.trace.is_some(),
+        options.binary_trace.is_some(),
+        profile,
+    ];
+    if options.ascii && recorders.contains(&true) {
+        return (
+            computer,
+            Err(anyhow!(
+                "traces and profiles can't be written in ASCII mode"
+            )),
+        );
+    }
+    if recorders.iter().filter(|&&recording| recording).count() > 1 {
+        return (
+            computer,
+            Err(anyhow!(
+                "only one kind of trace or profile can be written at a time"
+            )),
+        );
+    }
+
+    if options.ascii {
+        let stdin = std::io::stdin();
+        let stdout = std::io::stdout();
+        let mut ascii = AsciiComputer::new(computer);
+        let result = ascii.interact(stdin.lock(), stdout.lock());
+        return (
+            ascii.into_inner(),
+            result.map(|status| status == AsciiStatus::Halted),
+        );
+    }
+
+    if let Some(path) = &options.trace {
+        let writer = match create(path) {
+            Ok(writer) => writer,
+            Err(error) => return (computer, Err(error)),
+        };
+        let (computer, _, result) = run_traced(computer, JsonLinesWriter(writer));
+        (computer, result)
+    } else if let Some(path) = &options.binary_trace {
+        let writer = match create(path) {
+            Ok(writer) => writer,
+            Err(error) => return (computer, Err(error)),
+        };
+        let (computer, _, result) = run_traced(computer, BinaryTraceWriter::new(writer));
+        (computer, result)
+    } else if profile {
+        let base_len = computer.memory().base_len();
+        let (computer, profile, result) = run_traced(computer, Profile::new(base_len));
+
+        // the profile is still useful when the machine stops early
+        if options.profile {
+            eprint!("{}", profile);
+        }
+        if let Some(path) = &options.profile_folded {
+            let written = create(path).and_then(|writer| {
+                let result = profile.write_folded(writer);
+                result.with_context(|| format!("writing {}", path))
+            });
+            if let Err(error) = written {
+                return (computer, Err(error));
+            }
+        }
+        (computer, result)
+    } else {
+        let result = run_to_end(|input| {
+            if let Some(value) = input {
+                computer.provide_input(value);
+            }
+            Ok(computer.run()?)
+        });
+        (computer, result.map(|_| true))
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let options = parse_args()?;
+    let mut computer = match (&options.program, &options.resume) {
+        (Some(path), _) => {
+            let memory = load_program_file(path).with_context(|| format!("loading {}", path))?;
+            IntCodeComputer::new(&Program::from(memory))
+        }
+        (None, Some(path)) => {
+            let snapshot = Snapshot::load(path).with_context(|| format!("loading {}", path))?;
+            IntCodeComputer::from_snapshot(&snapshot)
+        }
+        (None, None) => unreachable!(),
+    };
+    if options.strict {
+        computer.set_validation(Validation::Strict);
+    }
+    computer.set_limits(options.limits);
+    for &value in &options.input {
+        computer.provide_input(value);
+    }
+
+    let (computer, result) = run(computer, &options);
+    if let Some(path) = &options.save {
+        // the instruction that stopped the machine hasn't run, so the machine
+        // can be resumed from there
+        if !matches!(result, Ok(true)) {
+            computer
+                .snapshot()
+                .save(path)
+                .with_context(|| format!("saving {}", path))?;
+            eprintln!("saved snapshot to {}", path);
+        }
+    }
+
+    result.map(|_| ())
+}
@@ -0,0 +1,1194 @@
+use crate::{
+    extension::{Argument, ExtensionCall},
+    ErrorKind, Extension, Instruction, IntcodeError, JournalEntry, Limits, Memory, MemoryBackend,
+    Mode, Opcode, PagedMemory, Program, Role, Snapshot, Word,
+};
+use itertools::Itertools;
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::TryFrom,
+    fmt::Display,
+    io::Write,
+    sync::Arc,
+    time::Instant,
+};
+
+pub type MemoryValue = i64;
+
+/// A custom opcode and the value of the instruction it's registered for.
+type CustomOpcode<B> = (MemoryValue, Arc<Extension<B>>);
+
+/// Why an [`IntCodeComputer`] stopped running.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum Status<W = MemoryValue> {
+    /// The program is waiting on an input instruction. Provide a value with
+    /// [`IntCodeComputer::provide_input`] and run again to resume.
+    NeedsInput,
+    /// The program wrote a value.
+    Output(W),
+    /// The program executed a halt instruction.
+    Halted,
+}
+
+/// How strictly an [`IntCodeComputer`] checks the instructions it executes.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Default)]
+pub enum Validation {
+    /// Mode digits for parameters an instruction doesn't take are ignored, and
+    /// writes to immediate mode parameters write to the parameter itself.
+    #[default]
+    Lenient,
+    /// Mode digits for parameters an instruction doesn't take and writes to
+    /// immediate mode parameters are faults.
+    Strict,
+}
+
+/// An Intcode virtual machine.
+///
+/// Each machine owns its memory, copying pages of the program image only when
+/// it writes to them, so machines are cheap to create and clone. The type of
+/// value the machine works with is the [`Word`] of its memory backend, which
+/// is a checked `i64` by default.
+#[derive(Clone, Debug)]
+pub struct IntCodeComputer<B: MemoryBackend = PagedMemory> {
+    memory: Memory<B>,
+    relative_base: i64,
+    ip: usize,
+    input: VecDeque<B::Word>,
+    steps: u64,
+    validation: Validation,
+    limits: Limits,
+    cache: Option<Vec<Option<Instruction>>>,
+    extensions: HashMap<MemoryValue, Arc<Extension<B>>>,
+}
+
+impl<W: Word> IntCodeComputer<PagedMemory<W>> {
+    pub fn new(program: &Program<W>) -> Self {
+        IntCodeComputer::with_backend(program)
+    }
+}
+
+impl<B: MemoryBackend> IntCodeComputer<B> {
+    /// Creates a machine that stores memory past the program image in the
+    /// given backend, like `IntCodeComputer::<SparseMemory>::with_backend`.
+    pub fn with_backend(program: &Program<B::Word>) -> Self {
+        IntCodeComputer {
+            memory: program.memory().with_backend(),
+            relative_base: 0,
+            ip: 0,
+            input: VecDeque::new(),
+            steps: 0,
+            validation: Validation::default(),
+            limits: Limits::none(),
+            cache: None,
+            extensions: HashMap::new(),
+        }
+    }
+
+    /// Creates a machine in the state captured by a snapshot, such as one
+    /// loaded with [`Snapshot::load`].
+    pub fn from_snapshot(snapshot: &Snapshot<B>) -> Self {
+        IntCodeComputer {
+            memory: snapshot.memory.clone(),
+            relative_base: snapshot.relative_base,
+            ip: snapshot.ip,
+            input: snapshot.input.clone(),
+            steps: snapshot.steps,
+            validation: Validation::default(),
+            limits: Limits::none(),
+            cache: None,
+            extensions: HashMap::new(),
+        }
+    }
+
+    pub fn validation(&self) -> Validation {
+        self.validation
+    }
+
+    /// Sets how strictly instructions are checked. Jumps to negative addresses
+    /// are faults in either mode.
+    pub fn set_validation(&mut self, validation: Validation) {
+        self.validation = validation;
+        self.clear_cache();
+    }
+
+    pub fn instruction_cache(&self) -> bool {
+        self.cache.is_some()
+    }
+
+    /// Sets whether decoded instructions in the program image are cached by
+    /// address, so each one is only decoded once until it's overwritten. This
+    /// speeds up long-running programs.
+    pub fn set_instruction_cache(&mut self, enabled: bool) {
+        self.cache = if enabled {
+            Some(vec![None; self.memory.base_len()])
+        } else {
+            None
+        };
+    }
+
+    fn clear_cache(&mut self) {
+        if let Some(cache) = &mut self.cache {
+            cache.iter_mut().for_each(|entry| *entry = None);
+        }
+    }
+
+    /// Registers a custom opcode, replacing any built-in or custom opcode with
+    /// the same number. Clones of the machine share the registered opcodes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `opcode` isn't in `0..=99`.
+    pub fn register_opcode(&mut self, opcode: MemoryValue, extension: Extension<B>) {
+        assert!((0..=99).contains(&opcode), "opcode {} out of range", opcode);
+        self.extensions.insert(opcode, Arc::new(extension));
+    }
+
+    /// Removes a custom opcode, restoring the built-in opcode if there is one.
+    pub fn unregister_opcode(&mut self, opcode: MemoryValue) -> Option<Arc<Extension<B>>> {
+        self.extensions.remove(&opcode)
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// The number of instructions executed so far. Input instructions that are
+    /// blocked waiting for input and halt instructions, which leave the
+    /// machine where it is, aren't counted.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Captures the current state of the machine.
+    pub fn snapshot(&self) -> Snapshot<B> {
+        Snapshot {
+            memory: self.memory.clone(),
+            relative_base: self.relative_base,
+            ip: self.ip,
+            input: self.input.clone(),
+            steps: self.steps,
+        }
+    }
+
+    /// Returns the machine to a previously captured state.
+    pub fn restore(&mut self, snapshot: &Snapshot<B>) {
+        self.memory = snapshot.memory.clone();
+        self.relative_base = snapshot.relative_base;
+        self.ip = snapshot.ip;
+        self.input = snapshot.input.clone();
+        self.steps = snapshot.steps;
+        self.clear_cache();
+    }
+
+    /// Queues a value to be consumed by the next input instruction.
+    pub fn provide_input(&mut self, value: B::Word) {
+        self.input.push_back(value);
+    }
+
+    pub fn pending_input(&self) -> &VecDeque<B::Word> {
+        &self.input
+    }
+
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    pub fn set_ip(&mut self, ip: usize) {
+        self.ip = ip;
+    }
+
+    pub fn relative_base(&self) -> i64 {
+        self.relative_base
+    }
+
+    pub fn set_relative_base(&mut self, relative_base: i64) {
+        self.relative_base = relative_base;
+    }
+
+    /// Reads the value stored at `addr`. Unwritten addresses read as 0.
+    pub fn get(&self, addr: usize) -> B::Word {
+        self.immediate(addr)
+    }
+
+    pub fn set(&mut self, addr: usize, value: B::Word) {
+        *self.immediate_mut(addr) = value;
+    }
+
+    /// Reverts the changes made by a journaled instruction.
+    pub(crate) fn undo(&mut self, entry: &JournalEntry) {
+        for write in entry.writes.iter().rev() {
+            self.set(write.addr, B::Word::from_i64(write.old));
+        }
+        if let Some(value) = entry.input {
+            self.input.push_front(B::Word::from_i64(value));
+        }
+        self.ip = entry.ip;
+        self.relative_base = entry.relative_base;
+        self.steps = entry.step;
+    }
+
+    pub fn memory(&self) -> &Memory<B> {
+        &self.memory
+    }
+
+    pub fn memory_mut(&mut self) -> &mut Memory<B> {
+        self.clear_cache();
+        &mut self.memory
+    }
+
+    fn immediate(&self, addr: usize) -> B::Word {
+        self.memory.get(addr)
+    }
+
+    fn immediate_mut(&mut self, addr: usize) -> &mut B::Word {
+        // self-modifying code invalidates the cached instruction
+        if let Some(entry) = self.cache.as_mut().and_then(|cache| cache.get_mut(addr)) {
+            *entry = None;
+        }
+
+        self.memory.get_mut(addr)
+    }
+
+    /// Builds an error for the instruction at the instruction pointer.
+    fn fault(&self, parameter: Option<usize>, kind: ErrorKind) -> IntcodeError {
+        self.fault_at(self.ip, parameter, kind)
+    }
+
+    fn fault_at(&self, ip: usize, parameter: Option<usize>, kind: ErrorKind) -> IntcodeError {
+        let instruction = self.immediate(ip);
+        IntcodeError {
+            ip,
+            instruction: instruction.to_i64().unwrap_or_else(|| {
+                if instruction < B::Word::default() {
+                    i64::MIN
+                } else {
+                    i64::MAX
+                }
+            }),
+            parameter,
+            kind,
+        }
+    }
+
+    fn to_i64(&self, parameter: Option<usize>, value: &B::Word) -> Result<i64, IntcodeError> {
+        value
+            .to_i64()
+            .ok_or_else(|| self.fault(parameter, ErrorKind::Overflow))
+    }
+
+    fn decode(&self) -> Result<Instruction, IntcodeError> {
+        let value = self.to_i64(None, &self.immediate(self.ip))?;
+        let instruction = Instruction::decode(value).map_err(|kind| {
+            let parameter = match kind {
+                ErrorKind::UnknownMode { parameter, .. } => Some(parameter),
+                _ => None,
+            };
+            self.fault(parameter, kind)
+        })?;
+
+        if self.validation == Validation::Strict {
+            let mut digits = value / 100;
+            let mut parameter = 0;
+            while digits != 0 {
+                if parameter >= instruction.opcode.arity() && digits % 10 != 0 {
+                    let kind = ErrorKind::UnexpectedMode {
+                        parameter,
+                        mode: digits % 10,
+                    };
+                    return Err(self.fault(Some(parameter), kind));
+                }
+
+                digits /= 10;
+                parameter += 1;
+            }
+        }
+
+        Ok(instruction)
+    }
+
+    fn to_address(&self, parameter: usize, value: i64) -> Result<usize, IntcodeError> {
+        let addr = usize::try_from(value)
+            .map_err(|_| self.fault(Some(parameter), ErrorKind::NegativeAddress(value)))?;
+        match self.limits.max_address {
+            Some(max_address) if addr >= max_address => {
+                Err(self.fault(Some(parameter), ErrorKind::AddressOutOfRange(addr)))
+            }
+            _ => Ok(addr),
+        }
+    }
+
+    /// Resolves the address the current instruction's parameter with the given
+    /// zero-based index refers to in the given mode. Immediate parameters
+    /// refer to their own address.
+    pub fn parameter_address(&self, mode: Mode, parameter: usize) -> Result<usize, IntcodeError> {
+        let addr = self.ip + 1 + parameter;
+        match mode {
+            Mode::Position => {
+                let value = self.to_i64(Some(parameter), &self.immediate(addr))?;
+                self.to_address(parameter, value)
+            }
+            Mode::Immediate => Ok(addr),
+            Mode::Relative => {
+                let offset = self.to_i64(Some(parameter), &self.immediate(addr))?;
+                let value = self
+                    .relative_base
+                    .checked_add(offset)
+                    .ok_or_else(|| self.fault(Some(parameter), ErrorKind::Overflow))?;
+                self.to_address(parameter, value)
+            }
+        }
+    }
+
+    fn get_parameter(&self, mode: Mode, parameter: usize) -> Result<B::Word, IntcodeError> {
+        let addr = self.parameter_address(mode, parameter)?;
+        Ok(self.immediate(addr))
+    }
+
+    /// Resolves the address a parameter written to by the current instruction
+    /// refers to.
+    fn output_address(&self, mode: Mode, parameter: usize) -> Result<usize, IntcodeError> {
+        if mode == Mode::Immediate && self.validation == Validation::Strict {
+            return Err(self.fault(Some(parameter), ErrorKind::WriteToImmediate));
+        }
+
+        self.parameter_address(mode, parameter)
+    }
+
+    fn get_parameter_mut(
+        &mut self,
+        mode: Mode,
+        parameter: usize,
+    ) -> Result<&mut B::Word, IntcodeError> {
+        let addr = self.output_address(mode, parameter)?;
+        self.check_allocation(parameter, addr)?;
+        Ok(self.immediate_mut(addr))
+    }
+
+    fn check_allocation(&self, parameter: usize, addr: usize) -> Result<(), IntcodeError> {
+        match self.limits.max_allocated {
+            Some(max_allocated)
+                if !self.memory.is_allocated(addr)
+                    && self.memory.allocated() + self.memory.allocation_size(addr)
+                        > max_allocated =>
+            {
+                Err(self.fault(
+                    Some(parameter),
+                    ErrorKind::MemoryLimitExceeded(max_allocated),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the limits before executing an instruction. Reading the clock is
+    /// slow compared to executing an instruction, so unless `check_deadline`
+    /// is set, the deadline is only checked every 256 instructions.
+    fn check_limits(&self, check_deadline: bool) -> Result<(), IntcodeError> {
+        if let Some(max_steps) = self.limits.max_steps {
+            if self.steps >= max_steps {
+                return Err(self.fault(None, ErrorKind::StepLimitExceeded(max_steps)));
+            }
+        }
+
+        if check_deadline || self.steps % 256 == 0 {
+            self.check_deadline()?;
+        }
+
+        Ok(())
+    }
+
+    fn check_deadline(&self) -> Result<(), IntcodeError> {
+        match self.limits.deadline {
+            Some(deadline) if Instant::now() >= deadline => {
+                Err(self.fault(None, ErrorKind::DeadlineExceeded))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn get_jump_target(&self, mode: Mode, parameter: usize) -> Result<usize, IntcodeError> {
+        let target = self.get_parameter(mode, parameter)?;
+        let target = self.to_i64(Some(parameter), &target)?;
+        self.to_address(parameter, target)
+    }
+
+    /// The custom opcode registered for the instruction at the instruction
+    /// pointer, if there is one, along with the instruction's value.
+    fn extension(&self) -> Result<Option<CustomOpcode<B>>, IntcodeError> {
+        if self.extensions.is_empty() {
+            return Ok(None);
+        }
+
+        let value = self.to_i64(None, &self.immediate(self.ip))?;
+        Ok(self
+            .extensions
+            .get(&(value % 100))
+            .map(|extension| (value, extension.clone())))
+    }
+
+    /// Whether the instruction at the instruction pointer runs a custom opcode.
+    pub(crate) fn at_custom_opcode(&self) -> bool {
+        matches!(self.extension(), Ok(Some(_)))
+    }
+
+    /// Decodes the modes of a custom instruction's parameters.
+    fn decode_modes(&self, value: i64, arity: usize) -> Result<Vec<Mode>, IntcodeError> {
+        let mut digits = value / 100;
+        let mut modes = Vec::with_capacity(arity);
+        for parameter in 0..arity {
+            let mode = Mode::from_value(digits % 10).ok_or_else(|| {
+                let kind = ErrorKind::UnknownMode {
+                    parameter,
+                    mode: digits % 10,
+                };
+                self.fault(Some(parameter), kind)
+            })?;
+            modes.push(mode);
+            digits /= 10;
+        }
+
+        let mut parameter = arity;
+        while self.validation == Validation::Strict && digits != 0 {
+            if digits % 10 != 0 {
+                let kind = ErrorKind::UnexpectedMode {
+                    parameter,
+                    mode: digits % 10,
+                };
+                return Err(self.fault(Some(parameter), kind));
+            }
+
+            digits /= 10;
+            parameter += 1;
+        }
+
+        Ok(modes)
+    }
+
+    fn execute_extension(
+        &mut self,
+        value: i64,
+        extension: &Extension<B>,
+    ) -> Result<Option<Status<B::Word>>, IntcodeError> {
+        let roles = extension.roles();
+        let modes = self.decode_modes(value, roles.len())?;
+        let mut arguments = Vec::with_capacity(roles.len());
+        for (parameter, (&role, &mode)) in roles.iter().zip(&modes).enumerate() {
+            arguments.push(match role {
+                Role::Read => Argument::Value(self.get_parameter(mode, parameter)?),
+                Role::Write => {
+                    let addr = self.output_address(mode, parameter)?;
+                    self.check_allocation(parameter, addr)?;
+                    Argument::Address(addr)
+                }
+            });
+        }
+
+        let ip = self.ip;
+        let mut call = ExtensionCall {
+            computer: self,
+            arguments,
+            jump: None,
+        };
+        let result = extension.call(&mut call);
+        let jump = call.jump;
+        let status =
+            result.map_err(|error| self.fault_at(ip, None, ErrorKind::Extension(error)))?;
+        if !matches!(status, Some(Status::NeedsInput) | Some(Status::Halted)) {
+            self.ip = jump.unwrap_or(ip + 1 + roles.len());
+        }
+
+        Ok(status)
+    }
+
+    /// The address the next instruction will write to, if it writes to memory.
+    /// For a custom opcode, this is the address of its first written
+    /// parameter, see [`IntCodeComputer::next_writes`].
+    pub fn next_write(&self) -> Result<Option<usize>, IntcodeError> {
+        Ok(self.next_writes()?.first().copied())
+    }
+
+    /// The addresses the next instruction can write to. Built-in instructions
+    /// write to at most one address, while custom opcodes can write to each of
+    /// their written parameters.
+    pub fn next_writes(&self) -> Result<Vec<usize>, IntcodeError> {
+        if let Some((value, extension)) = self.extension()? {
+            let modes = self.decode_modes(value, extension.roles().len())?;
+            return extension
+                .roles()
+                .iter()
+                .zip(modes)
+                .enumerate()
+                .filter(|(_, (&role, _))| role == Role::Write)
+                .map(|(index, (_, mode))| self.output_address(mode, index))
+                .collect();
+        }
+
+        let instruction = self.decode()?;
+        match instruction.opcode.output_parameter() {
+            Some(index) => {
+                let addr = self.output_address(instruction.modes[index], index)?;
+                Ok(vec![addr])
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Executes a single instruction. Returns a status if the instruction
+    /// halted, produced output, or is blocked waiting for input, in which case
+    /// the instruction pointer is left on the input instruction.
+    pub fn step(&mut self) -> Result<Option<Status<B::Word>>, IntcodeError> {
+        self.step_checked(true)
+    }
+
+    /// Executes a single instruction, checking the deadline before it if
+    /// `check_deadline` is set, and otherwise only every few hundred
+    /// instructions or before a custom opcode, which could be slow.
+    fn step_checked(
+        &mut self,
+        check_deadline: bool,
+    ) -> Result<Option<Status<B::Word>>, IntcodeError> {
+        self.check_limits(check_deadline)?;
+        if let Some((value, extension)) = self.extension()? {
+            if !check_deadline {
+                self.check_deadline()?;
+            }
+
+            let status = self.execute_extension(value, &extension)?;
+            if !matches!(status, Some(Status::NeedsInput) | Some(Status::Halted)) {
+                self.steps += 1;
+            }
+
+            return Ok(status);
+        }
+
+        let ip = self.ip;
+        let cached = self.cache.as_ref().and_then(|cache| cache.get(ip));
+        let instruction = match cached {
+            Some(&Some(instruction)) => instruction,
+            _ => {
+                let instruction = self.decode()?;
+                if let Some(entry) = self.cache.as_mut().and_then(|cache| cache.get_mut(ip)) {
+                    *entry = Some(instruction);
+                }
+                instruction
+            }
+        };
+        let status = self.execute(instruction)?;
+        if !matches!(status, Some(Status::NeedsInput) | Some(Status::Halted)) {
+            self.steps += 1;
+        }
+
+        Ok(status)
+    }
+
+    fn execute(
+        &mut self,
+        instruction: Instruction,
+    ) -> Result<Option<Status<B::Word>>, IntcodeError> {
+        let [p1_mode, p2_mode, p3_mode] = instruction.modes;
+        match instruction.opcode {
+            Opcode::Add => {
+                // add
+                let a = self.get_parameter(p1_mode, 0)?;
+                let b = self.get_parameter(p2_mode, 1)?;
+                let sum = a
+                    .checked_add(&b)
+                    .ok_or_else(|| self.fault(None, ErrorKind::Overflow))?;
+                let target = self.get_parameter_mut(p3_mode, 2)?;
+                *target = sum;
+
+                self.ip += 4;
+            }
+            Opcode::Multiply => {
+                // multiply
+                let a = self.get_parameter(p1_mode, 0)?;
+                let b = self.get_parameter(p2_mode, 1)?;
+                let product = a
+                    .checked_mul(&b)
+                    .ok_or_else(|| self.fault(None, ErrorKind::Overflow))?;
+                let target = self.get_parameter_mut(p3_mode, 2)?;
+                *target = product;
+
+                self.ip += 4;
+            }
+            Opcode::Input => {
+                // read int
+                let addr = self.output_address(p1_mode, 0)?;
+                self.check_allocation(0, addr)?;
+                let input = match self.input.pop_front() {
+                    Some(input) => input,
+                    None => return Ok(Some(Status::NeedsInput)),
+                };
+                *self.immediate_mut(addr) = input;
+
+                self.ip += 2;
+            }
+            Opcode::Output => {
+                // write int
+                let value = self.get_parameter(p1_mode, 0)?;
+
+                self.ip += 2;
+                return Ok(Some(Status::Output(value)));
+            }
+            Opcode::JumpIfTrue => {
+                // jump if true
+                let condition = self.get_parameter(p1_mode, 0)?;
+                let target = self.get_jump_target(p2_mode, 1)?;
+
+                if !condition.is_zero() {
+                    self.ip = target;
+                } else {
+                    self.ip += 3;
+                }
+            }
+            Opcode::JumpIfFalse => {
+                // jump if false
+                let condition = self.get_parameter(p1_mode, 0)?;
+                let target = self.get_jump_target(p2_mode, 1)?;
+
+                if condition.is_zero() {
+                    self.ip = target;
+                } else {
+                    self.ip += 3;
+                }
+            }
+            Opcode::LessThan => {
+                // less than
+                let a = self.get_parameter(p1_mode, 0)?;
+                let b = self.get_parameter(p2_mode, 1)?;
+                let target = self.get_parameter_mut(p3_mode, 2)?;
+                *target = B::Word::from_i64(if a < b { 1 } else { 0 });
+
+                self.ip += 4;
+            }
+            Opcode::Equals => {
+                // equals
+                let a = self.get_parameter(p1_mode, 0)?;
+                let b = self.get_parameter(p2_mode, 1)?;
+                let target = self.get_parameter_mut(p3_mode, 2)?;
+                *target = B::Word::from_i64(if a == b { 1 } else { 0 });
+
+                self.ip += 4;
+            }
+            Opcode::AdjustRelativeBase => {
+                // adjust relative base
+                let offset = self.get_parameter(p1_mode, 0)?;
+                let offset = self.to_i64(Some(0), &offset)?;
+                self.relative_base = self
+                    .relative_base
+                    .checked_add(offset)
+                    .ok_or_else(|| self.fault(Some(0), ErrorKind::Overflow))?;
+
+                self.ip += 2;
+            }
+            Opcode::Halt => {
+                return Ok(Some(Status::Halted));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Runs the program until it halts, needs input, or writes output. Running
+    /// again after an output resumes execution from the next instruction.
+    pub fn run(&mut self) -> Result<Status<B::Word>, IntcodeError> {
+        self.check_deadline()?;
+        loop {
+            if let Some(status) = self.step_checked(false)? {
+                return Ok(status);
+            }
+        }
+    }
+
+    /// Runs the program until it halts, requesting input from `read` and
+    /// passing output to `write`, and returns the number of instructions
+    /// executed. The program fails if `read` runs out of input.
+    pub fn run_with<R, W>(&mut self, mut read: R, mut write: W) -> Result<usize, IntcodeError>
+    where
+        R: FnMut() -> anyhow::Result<Option<B::Word>>,
+        W: FnMut(B::Word) -> anyhow::Result<()>,
+    {
+        self.check_deadline()?;
+        let mut steps = 0;
+        loop {
+            let ip = self.ip;
+            match self.step_checked(false)? {
+                None => steps += 1,
+                Some(Status::NeedsInput) => {
+                    let input = read()
+                        .map_err(|error| self.fault(None, ErrorKind::Callback(error)))?
+                        .ok_or_else(|| self.fault(Some(0), ErrorKind::InputExhausted))?;
+                    self.provide_input(input);
+                }
+                Some(Status::Output(value)) => {
+                    steps += 1;
+                    write(value)
+                        .map_err(|error| self.fault_at(ip, None, ErrorKind::Callback(error)))?;
+                }
+                Some(Status::Halted) => return Ok(steps),
+            }
+        }
+    }
+
+    /// Writes the instruction pointer, relative base and memory in a human
+    /// readable form, marking the cell at the instruction pointer.
+    pub fn dump<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        fn cell(ip: usize, addr: usize, value: impl Display) -> String {
+            let marker = if addr == ip { '*' } else { ' ' };
+            format!("{}[{:4}]: {:5}", marker, addr, value)
+        }
+
+        writeln!(writer, "ip = {}", self.ip)?;
+        writeln!(writer, "relative base = {}", self.relative_base)?;
+        writeln!(
+            writer,
+            "base memory = [\n{}\n]",
+            self.memory
+                .base()
+                .enumerate()
+                .map(|(addr, value)| cell(self.ip, addr, value))
+                .chunks(5)
+                .into_iter()
+                .map(|inner| inner.format(", "))
+                .format(",\n")
+        )?;
+        writeln!(
+            writer,
+            "additional memory = {{\n{}\n}}",
+            self.memory
+                .additional()
+                .values()
+                .sorted()
+                .map(|(addr, value)| cell(self.ip, addr, value))
+                .chunks(5)
+                .into_iter()
+                .map(|inner| inner.format(", "))
+                .format(",\n")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_with_input(
+        program: &[MemoryValue],
+        input: &[MemoryValue],
+    ) -> Result<(IntCodeComputer, Vec<MemoryValue>), IntcodeError> {
+        let mut computer = IntCodeComputer::new(&program.into());
+        let mut input = input.iter().copied();
+        let mut output = Vec::new();
+        computer.run_with(
+            || Ok(input.next()),
+            |value| {
+                output.push(value);
+                Ok(())
+            },
+        )?;
+        Ok((computer, output))
+    }
+
+    fn final_memory(program: &[MemoryValue]) -> Vec<MemoryValue> {
+        let (computer, _) = run_with_input(program, &[]).unwrap();
+        computer.memory().base().collect()
+    }
+
+    fn output(program: &[MemoryValue], input: &[MemoryValue]) -> Vec<MemoryValue> {
+        run_with_input(program, input).unwrap().1
+    }
+
+    fn assembled_output(source: &str, input: &[MemoryValue]) -> Vec<MemoryValue> {
+        output(&crate::assemble(source).unwrap(), input)
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(
+            final_memory(&[1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]),
+            [3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50]
+        );
+        assert_eq!(
+            final_memory(&[1, 1, 1, 4, 99, 5, 6, 0, 99]),
+            [30, 1, 1, 4, 2, 5, 6, 0, 99]
+        );
+    }
+
+    #[test]
+    fn parameter_modes() {
+        assert_eq!(final_memory(&[1002, 4, 3, 4, 33]), [1002, 4, 3, 4, 99]);
+        assert_eq!(final_memory(&[1101, 100, -1, 4, 0]), [1101, 100, -1, 4, 99]);
+    }
+
+    #[test]
+    fn comparisons() {
+        let equal_to_8 = [3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+        let less_than_8 = [3, 3, 1107, -1, 8, 3, 4, 3, 99];
+        for &(input, equal, less) in &[(7, 0, 1), (8, 1, 0), (9, 0, 0)] {
+            assert_eq!(output(&equal_to_8, &[input]), [equal]);
+            assert_eq!(output(&less_than_8, &[input]), [less]);
+        }
+    }
+
+    #[test]
+    fn jumps() {
+        let program = [3, 12, 6, 12, 15, 1, 13, 14, 13, 4, 13, 99, -1, 0, 1, 9];
+        assert_eq!(output(&program, &[0]), [0]);
+        assert_eq!(output(&program, &[5]), [1]);
+    }
+
+    #[test]
+    fn relative_mode_quine() {
+        let program = [
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        assert_eq!(output(&program, &[]), program);
+    }
+
+    #[test]
+    fn large_numbers() {
+        assert_eq!(
+            output(&[104, 1125899906842624, 99], &[]),
+            [1125899906842624]
+        );
+        assert_eq!(
+            output(&[1102, 34915192, 34915192, 7, 4, 7, 99, 0], &[]),
+            [1219070632396864]
+        );
+    }
+
+    #[test]
+    fn each_opcode() {
+        let source = "
+            IN -> [a]
+            IN -> [b]
+            ADD [a], [b] -> [out]
+            OUT [out]
+            MUL [a], #-3 -> [out]
+            OUT [out]
+            LT [a], [b] -> [out]
+            OUT [out]
+            EQ [a], #4 -> [out]
+            OUT [out]
+            HLT
+            a: DATA 0
+            b: DATA 0
+            out: DATA 0
+        ";
+        assert_eq!(assembled_output(source, &[4, 6]), [10, -12, 1, 1]);
+        assert_eq!(assembled_output(source, &[6, 4]), [10, -18, 0, 0]);
+
+        let source = "
+            IN -> [cond]
+            JT [cond], #yes
+            OUT #0
+            JF [cond], #end
+            yes: OUT #1
+            end: HLT
+            cond: DATA 0
+        ";
+        assert_eq!(assembled_output(source, &[0]), [0]);
+        assert_eq!(assembled_output(source, &[-2]), [1]);
+    }
+
+    #[test]
+    fn relative_base() {
+        let source = "
+            ARB #values
+            OUT [rb+1]
+            ARB #2
+            OUT [rb-1]
+            IN -> [rb]
+            OUT [rb]
+            ARB [rb]
+            OUT [rb+0]
+            HLT
+            values: DATA 10, 20, 30, 1
+        ";
+        assert_eq!(assembled_output(source, &[1]), [20, 20, 1, 1]);
+    }
+
+    #[test]
+    fn additional_memory() {
+        fn run<B: MemoryBackend<Word = MemoryValue>>() -> IntCodeComputer<B> {
+            let program = Program::new(&[1101, 2, 3, 1000, 4, 1000, 99]);
+            let mut computer = IntCodeComputer::with_backend(&program);
+            assert_eq!(computer.run().unwrap(), Status::Output(5));
+            assert_eq!(computer.run().unwrap(), Status::Halted);
+            assert_eq!(computer.get(1000), 5);
+            assert_eq!(computer.get(2000), 0);
+            computer
+        }
+
+        let computer = run::<crate::SparseMemory>();
+        assert_eq!(computer.memory().allocated(), 1);
+        let computer = run::<PagedMemory>();
+        assert_eq!(computer.memory().additional().values().count(), 1);
+    }
+
+    #[test]
+    fn resumable() {
+        // doubles each input until it reads 0
+        let program = Program::new(&[
+            3, 15, 1006, 15, 14, 102, 2, 15, 15, 4, 15, 1105, 1, 0, 99, 0,
+        ]);
+        let mut computer = IntCodeComputer::new(&program);
+        assert_eq!(computer.run().unwrap(), Status::NeedsInput);
+        assert_eq!(computer.ip(), 0);
+        assert_eq!(computer.run().unwrap(), Status::NeedsInput);
+
+        computer.provide_input(3);
+        computer.provide_input(5);
+        assert_eq!(computer.run().unwrap(), Status::Output(6));
+        assert_eq!(computer.run().unwrap(), Status::Output(10));
+        assert_eq!(computer.run().unwrap(), Status::NeedsInput);
+        assert!(computer.pending_input().is_empty());
+
+        computer.provide_input(0);
+        assert_eq!(computer.run().unwrap(), Status::Halted);
+        let steps = computer.steps();
+        assert_eq!(computer.run().unwrap(), Status::Halted);
+        assert_eq!(computer.steps(), steps);
+    }
+
+    #[test]
+    fn step_count() {
+        let steps = IntCodeComputer::new(&Program::new(&[3, 0, 4, 0, 99]))
+            .run_with(|| Ok(Some(7)), |_| Ok(()))
+            .unwrap();
+        assert_eq!(steps, 2);
+    }
+
+    #[test]
+    fn programs_are_not_modified() {
+        let program = Program::new(&[1101, 2, 3, 0, 99]);
+        let mut computer = IntCodeComputer::new(&program);
+        computer.run().unwrap();
+        assert_eq!(computer.get(0), 5);
+        assert_eq!(program.memory().get(0), 1101);
+        assert_eq!(IntCodeComputer::new(&program).get(0), 1101);
+    }
+
+    #[test]
+    fn clone_and_snapshot() {
+        // counts up from its input, writing each value
+        let program = Program::new(&[3, 11, 4, 11, 1001, 11, 1, 11, 1105, 1, 2]);
+        let mut computer = IntCodeComputer::new(&program);
+        computer.provide_input(10);
+        assert_eq!(computer.run().unwrap(), Status::Output(10));
+
+        let snapshot = computer.snapshot();
+        let mut fork = computer.clone();
+        assert_eq!(computer.run().unwrap(), Status::Output(11));
+        assert_eq!(computer.run().unwrap(), Status::Output(12));
+        assert_eq!(fork.run().unwrap(), Status::Output(11));
+
+        computer.restore(&snapshot);
+        assert_eq!(computer.snapshot(), snapshot);
+        assert_eq!(computer.run().unwrap(), Status::Output(11));
+    }
+
+    #[test]
+    fn dump() {
+        let mut computer = IntCodeComputer::new(&Program::new(&[1101, 1, 2, 10, 104, 7, 99]));
+        assert_eq!(computer.run().unwrap(), Status::Output(7));
+        let mut dump = Vec::new();
+        computer.dump(&mut dump).unwrap();
+        assert_eq!(
+            String::from_utf8(dump).unwrap(),
+            "ip = 6\n\
+             relative base = 0\n\
+             base memory = [\n\
+             \x20[   0]:  1101,  [   1]:     1,  [   2]:     2,  [   3]:    10,  [   4]:   104,\n\
+             \x20[   5]:     7, *[   6]:    99\n\
+             ]\n\
+             additional memory = {\n\
+             \x20[  10]:     3\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn instruction_cache() {
+        // outputs its value, then replaces the output with a halt
+        let program = crate::assemble(
+            "
+            start:  OUT [value]
+                    ADD #0, #99 -> [start]
+                    JT #1, #start
+            value:  DATA 7
+            ",
+        )
+        .unwrap();
+        let mut computer = IntCodeComputer::new(&program.as_slice().into());
+        computer.set_instruction_cache(true);
+        assert_eq!(computer.run().unwrap(), Status::Output(7));
+        assert_eq!(computer.run().unwrap(), Status::Halted);
+
+        // patches made through the machine's memory aren't missed either
+        let mut computer = IntCodeComputer::new(&program.as_slice().into());
+        computer.set_instruction_cache(true);
+        assert_eq!(computer.run().unwrap(), Status::Output(7));
+        computer.set_ip(0);
+        computer.memory_mut().set(0, 104);
+        assert_eq!(computer.run().unwrap(), Status::Output(9));
+
+        // cached strict checks are redone when the validation changes
+        let mut computer = IntCodeComputer::new(&[1104, 1, 1105, 1, 0].as_ref().into());
+        computer.set_instruction_cache(true);
+        assert_eq!(computer.run().unwrap(), Status::Output(1));
+        computer.set_validation(Validation::Strict);
+        assert!(computer.run().is_err());
+    }
+
+    #[test]
+    fn missing_input() {
+        let error = run_with_input(&[1101, 0, 0, 0, 3, 0, 99], &[]).unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::InputExhausted));
+        assert_eq!((error.ip, error.instruction), (4, 3));
+        assert!(error.is_fault());
+    }
+
+    #[test]
+    fn unknown_opcode() {
+        let error = run_with_input(&[42], &[]).unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::UnknownOpcode(42)));
+        assert_eq!((error.ip, error.parameter), (0, None));
+
+        let error = run_with_input(&[104, 1, 304, 0, 99], &[]).unwrap_err();
+        assert!(matches!(
+            error.kind,
+            ErrorKind::UnknownMode {
+                parameter: 0,
+                mode: 3
+            }
+        ));
+        assert_eq!((error.ip, error.parameter), (2, Some(0)));
+    }
+
+    #[test]
+    fn negative_addresses() {
+        let error = run_with_input(&[1, -1, 0, 0, 99], &[]).unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::NegativeAddress(-1)));
+        assert_eq!(error.parameter, Some(0));
+
+        let error = run_with_input(&[109, -5, 22201, 0, 0, 0, 99], &[]).unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::NegativeAddress(-5)));
+        assert_eq!((error.ip, error.instruction), (2, 22201));
+
+        let error = run_with_input(&[1105, 1, -3, 99], &[]).unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::NegativeAddress(-3)));
+        assert_eq!(error.parameter, Some(1));
+    }
+
+    #[test]
+    fn validation() {
+        fn run(program: &[MemoryValue], validation: Validation) -> Result<Status, IntcodeError> {
+            let mut computer = IntCodeComputer::new(&program.into());
+            computer.set_validation(validation);
+            computer.run()
+        }
+
+        // writes to the immediate parameter in lenient mode
+        let program = [11101, 2, 3, 0, 99];
+        let mut computer = IntCodeComputer::new(&program.as_ref().into());
+        assert_eq!(computer.run().unwrap(), Status::Halted);
+        assert_eq!(computer.get(3), 5);
+        let error = run(&[11101, 2, 3, 0, 99], Validation::Strict).unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::WriteToImmediate));
+        assert_eq!(error.parameter, Some(2));
+        let error = run(&[103, 0, 99], Validation::Strict).unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::WriteToImmediate));
+
+        // mode digits for parameters the instruction doesn't take
+        assert_eq!(
+            run(&[1104, 7], Validation::Lenient).unwrap(),
+            Status::Output(7)
+        );
+        assert_eq!(run(&[99999], Validation::Lenient).unwrap(), Status::Halted);
+        assert_eq!(
+            run(&[104, 7], Validation::Strict).unwrap(),
+            Status::Output(7)
+        );
+        let error = run(&[1104, 7], Validation::Strict).unwrap_err();
+        assert!(matches!(
+            error.kind,
+            ErrorKind::UnexpectedMode {
+                parameter: 1,
+                mode: 1
+            }
+        ));
+        let error = run(&[100001101, 1, 1, 0], Validation::Strict).unwrap_err();
+        assert_eq!(error.parameter, Some(6));
+
+        for &validation in &[Validation::Lenient, Validation::Strict] {
+            let error = run(&[1105, 1, -3], validation).unwrap_err();
+            assert!(matches!(error.kind, ErrorKind::NegativeAddress(-3)));
+        }
+    }
+
+    #[test]
+    fn limits() {
+        let forever = crate::assemble("loop: JT #1, #loop").unwrap();
+        let mut computer = IntCodeComputer::new(&forever.as_slice().into());
+        computer.set_limits(Limits::none().with_max_steps(100));
+        let error = computer.run().unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::StepLimitExceeded(100)));
+        assert_eq!(computer.steps(), 100);
+
+        let mut computer = IntCodeComputer::new(&forever.as_slice().into());
+        computer.set_limits(Limits::none().with_timeout(std::time::Duration::from_millis(10)));
+        let error = computer.run().unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::DeadlineExceeded));
+
+        // the deadline is checked on every step and whenever the machine runs
+        let mut computer = IntCodeComputer::new(&forever.as_slice().into());
+        computer.step().unwrap();
+        computer.set_limits(Limits::none().with_timeout(std::time::Duration::ZERO));
+        let error = computer.step().unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::DeadlineExceeded));
+        let error = computer.run().unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::DeadlineExceeded));
+        assert_eq!(computer.steps(), 1);
+
+        let mut computer = IntCodeComputer::new(&[4, 1000, 99].as_ref().into());
+        computer.set_limits(Limits::none().with_max_address(1000));
+        let error = computer.run().unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::AddressOutOfRange(1000)));
+        assert_eq!(error.parameter, Some(0));
+        assert_eq!(computer.steps(), 0);
+
+        // writes to addresses past the program until it runs out of memory
+        let program = crate::assemble(
+            "
+            loop:   ADD #0, #1 -> [rb]
+                    ARB #1
+                    JT #1, #loop
+            ",
+        )
+        .unwrap();
+        let mut computer = IntCodeComputer::new(&program.as_slice().into());
+        computer.set_relative_base(100);
+        computer.set_limits(Limits::none().with_max_allocated(2000));
+        let error = computer.run().unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::MemoryLimitExceeded(2000)));
+        assert!(computer.memory().allocated() <= 2000);
+        assert_eq!(computer.get(1023), 1);
+        assert_eq!(computer.get(1024), 0);
+    }
+
+    #[test]
+    fn callback_errors() {
+        let mut computer = IntCodeComputer::new(&[104, 7, 99].as_ref().into());
+        let error = computer
+            .run_with(|| Ok(None), |_| anyhow::bail!("closed"))
+            .unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::Callback(_)));
+        assert_eq!((error.ip, error.instruction), (0, 104));
+        assert!(!error.is_fault());
+    }
+}
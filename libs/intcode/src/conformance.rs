@@ -0,0 +1,586 @@
+use crate::{
+    IntCodeComputer, IntcodeError, Limits, MemoryBackend, MemoryValue, Mode, Opcode, Program,
+    Validation, Word,
+};
+use itertools::Itertools;
+use std::fmt::{self, Display, Formatter};
+
+/// How many instructions a case can run before it's considered stuck.
+const MAX_STEPS: u64 = 10_000;
+
+/// Where generated programs keep the values their parameters refer to.
+const DATA: usize = 64;
+
+/// The relative base generated programs set, past their data so relative
+/// parameters use negative offsets.
+const RELATIVE_BASE: i64 = 70;
+
+/// What running a program did.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    /// The program halted after writing these values.
+    Halted(Vec<MemoryValue>),
+    /// The program faulted. The kind is the error's message, like
+    /// `unknown opcode 42`.
+    Fault {
+        ip: usize,
+        parameter: Option<usize>,
+        kind: String,
+    },
+}
+
+impl Outcome {
+    fn fault(ip: usize, parameter: Option<usize>, kind: &str) -> Self {
+        Outcome::Fault {
+            ip,
+            parameter,
+            kind: kind.to_string(),
+        }
+    }
+}
+
+impl From<IntcodeError> for Outcome {
+    fn from(error: IntcodeError) -> Self {
+        Outcome::Fault {
+            ip: error.ip,
+            parameter: error.parameter,
+            kind: error.kind.to_string(),
+        }
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Halted(output) => {
+                write!(f, "halted with output [{}]", output.iter().join(", "))
+            }
+            Outcome::Fault {
+                ip,
+                parameter,
+                kind,
+            } => {
+                write!(f, "{} at {}", kind, ip)?;
+                if let Some(parameter) = parameter {
+                    write!(f, ", parameter {}", parameter)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A program with the outcome every Intcode machine should produce when
+/// running it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Case {
+    pub name: String,
+    pub program: Vec<MemoryValue>,
+    pub input: Vec<MemoryValue>,
+    pub validation: Validation,
+    /// Whether the outcome depends on words faulting when they overflow an
+    /// `i64`, so the case only applies to machines with such words.
+    pub overflows: bool,
+    pub expected: Outcome,
+}
+
+impl Case {
+    fn new(
+        name: String,
+        program: Vec<MemoryValue>,
+        input: &[MemoryValue],
+        expected: Outcome,
+    ) -> Self {
+        Case {
+            name,
+            program,
+            input: input.to_vec(),
+            validation: Validation::Lenient,
+            overflows: false,
+            expected,
+        }
+    }
+
+    fn strict(self) -> Self {
+        Case {
+            validation: Validation::Strict,
+            ..self
+        }
+    }
+
+    /// Whether the case applies to machines with words of type `W`.
+    pub fn applies_to<W: Word>(&self) -> bool {
+        !self.overflows || W::from_i64(i64::MAX).checked_add(&W::from_i64(1)).is_none()
+    }
+
+    /// Runs the program on a machine with the given backend, within a limit
+    /// of steps so programs that don't halt fault instead.
+    pub fn run<B: MemoryBackend>(&self) -> Outcome {
+        let program: Vec<_> = self
+            .program
+            .iter()
+            .map(|&value| B::Word::from_i64(value))
+            .collect();
+        let mut computer = IntCodeComputer::<B>::with_backend(&Program::new(&program));
+        computer.set_validation(self.validation);
+        computer.set_limits(Limits::none().with_max_steps(MAX_STEPS));
+
+        let mut input = self.input.iter().map(|&value| B::Word::from_i64(value));
+        let mut output = Vec::new();
+        let result = computer.run_with(
+            || Ok(input.next()),
+            |value| match value.to_i64() {
+                Some(value) => {
+                    output.push(value);
+                    Ok(())
+                }
+                None => anyhow::bail!("output {} doesn't fit in an i64", value),
+            },
+        );
+
+        match result {
+            Ok(_) => Outcome::Halted(output),
+            Err(error) => error.into(),
+        }
+    }
+}
+
+/// A case whose program didn't produce the expected outcome.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Mismatch {
+    pub case: String,
+    pub expected: Outcome,
+    pub actual: Outcome,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: expected {}, got {}",
+            self.case, self.expected, self.actual
+        )
+    }
+}
+
+/// Runs every conformance case that applies to the backend's words, returning
+/// the cases that didn't produce their expected outcome.
+pub fn check_conformance<B: MemoryBackend>() -> Vec<Mismatch> {
+    cases()
+        .into_iter()
+        .filter(|case| case.applies_to::<B::Word>())
+        .filter_map(|case| {
+            let actual = case.run::<B>();
+            if actual == case.expected {
+                None
+            } else {
+                Some(Mismatch {
+                    case: case.name,
+                    expected: case.expected,
+                    actual,
+                })
+            }
+        })
+        .collect()
+}
+
+/// The conformance suite: the example programs from the puzzles, every
+/// opcode with every combination of parameter modes, and programs that fault.
+pub fn cases() -> Vec<Case> {
+    let mut cases = examples();
+    for &opcode in &Opcode::ALL {
+        cases.extend(opcode_cases(opcode));
+    }
+    cases.extend(faults());
+    cases
+}
+
+fn examples() -> Vec<Case> {
+    let mut cases = Vec::new();
+
+    let comparisons: [(&str, &[MemoryValue]); 4] = [
+        (
+            "equal to 8, position",
+            &[3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8],
+        ),
+        (
+            "less than 8, position",
+            &[3, 9, 7, 9, 10, 9, 4, 9, 99, -1, 8],
+        ),
+        ("equal to 8, immediate", &[3, 3, 1108, -1, 8, 3, 4, 3, 99]),
+        ("less than 8, immediate", &[3, 3, 1107, -1, 8, 3, 4, 3, 99]),
+    ];
+    for &(name, program) in &comparisons {
+        for &input in &[7, 8, 9] {
+            let output = if name.starts_with("equal") {
+                input == 8
+            } else {
+                input < 8
+            };
+            cases.push(Case::new(
+                format!("{} with input {}", name, input),
+                program.to_vec(),
+                &[input],
+                Outcome::Halted(vec![output as MemoryValue]),
+            ));
+        }
+    }
+
+    let jumps: [(&str, &[MemoryValue]); 2] = [
+        (
+            "jump, position",
+            &[3, 12, 6, 12, 15, 1, 13, 14, 13, 4, 13, 99, -1, 0, 1, 9],
+        ),
+        (
+            "jump, immediate",
+            &[3, 3, 1105, -1, 9, 1101, 0, 0, 12, 4, 12, 99, 1],
+        ),
+    ];
+    for &(name, program) in &jumps {
+        for &input in &[0, 5] {
+            cases.push(Case::new(
+                format!("{} with input {}", name, input),
+                program.to_vec(),
+                &[input],
+                Outcome::Halted(vec![(input != 0) as MemoryValue]),
+            ));
+        }
+    }
+
+    let compare_to_8 = [
+        3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0, 0,
+        1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4, 20,
+        1105, 1, 46, 98, 99,
+    ];
+    for &(input, output) in &[(7, 999), (8, 1000), (9, 1001)] {
+        cases.push(Case::new(
+            format!("compare to 8 with input {}", input),
+            compare_to_8.to_vec(),
+            &[input],
+            Outcome::Halted(vec![output]),
+        ));
+    }
+
+    let quine = [
+        109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+    ];
+    cases.push(Case::new(
+        "quine".to_string(),
+        quine.to_vec(),
+        &[],
+        Outcome::Halted(quine.to_vec()),
+    ));
+    cases.push(Case::new(
+        "large product".to_string(),
+        vec![1102, 34915192, 34915192, 7, 4, 7, 99, 0],
+        &[],
+        Outcome::Halted(vec![1219070632396864]),
+    ));
+    cases.push(Case::new(
+        "large output".to_string(),
+        vec![104, 1125899906842624, 99],
+        &[],
+        Outcome::Halted(vec![1125899906842624]),
+    ));
+
+    cases
+}
+
+/// A program that sets the relative base, then executes `instruction`, with
+/// `values` stored in its data.
+fn generated(
+    instruction: &[MemoryValue],
+    values: &[MemoryValue],
+    tail: &[MemoryValue],
+) -> Vec<MemoryValue> {
+    let mut program = vec![109, RELATIVE_BASE];
+    program.extend_from_slice(instruction);
+    program.extend_from_slice(tail);
+    assert!(program.len() <= DATA);
+    program.resize(DATA, 0);
+    program.extend_from_slice(values);
+    program
+}
+
+/// The parameter referring to the data value in `slot` in the given mode, or
+/// `value` itself in immediate mode.
+fn parameter(mode: Mode, slot: usize, value: MemoryValue) -> MemoryValue {
+    let addr = (DATA + slot) as MemoryValue;
+    match mode {
+        Mode::Position => addr,
+        Mode::Immediate => value,
+        Mode::Relative => addr - RELATIVE_BASE,
+    }
+}
+
+fn instruction_value(opcode: Opcode, modes: &[Mode]) -> MemoryValue {
+    modes
+        .iter()
+        .rev()
+        .fold(0, |digits, mode| digits * 10 + mode.value())
+        * 100
+        + opcode.value()
+}
+
+fn mode_name(mode: Mode) -> &'static str {
+    match mode {
+        Mode::Position => "position",
+        Mode::Immediate => "immediate",
+        Mode::Relative => "relative",
+    }
+}
+
+fn case_name(opcode: Opcode, modes: &[Mode]) -> String {
+    let modes = modes.iter().map(|&mode| mode_name(mode)).join(", ");
+    format!("{} ({})", opcode.mnemonic(), modes)
+}
+
+/// Cases running the opcode with every combination of parameter modes. Values
+/// written in immediate mode overwrite the parameter itself.
+fn opcode_cases(opcode: Opcode) -> Vec<Case> {
+    const MODES: [Mode; 3] = [Mode::Position, Mode::Immediate, Mode::Relative];
+
+    let combinations = (0..opcode.arity())
+        .map(|_| MODES.iter().copied())
+        .multi_cartesian_product();
+    let mut cases = Vec::new();
+    for modes in combinations {
+        let value = instruction_value(opcode, &modes);
+        let name = case_name(opcode, &modes);
+        let params = |values: &[MemoryValue]| -> Vec<MemoryValue> {
+            let mut instruction = vec![value];
+            instruction.extend(
+                modes
+                    .iter()
+                    .zip(values)
+                    .enumerate()
+                    .map(|(slot, (&mode, &value))| parameter(mode, slot, value)),
+            );
+            instruction
+        };
+
+        // writes go to the parameter itself in immediate mode
+        let written = |parameter: usize| match modes[parameter] {
+            Mode::Immediate => 3 + parameter as MemoryValue,
+            _ => (DATA + parameter) as MemoryValue,
+        };
+
+        match opcode {
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equals => {
+                let (a, b) = (6, 7);
+                let result = match opcode {
+                    Opcode::Add => a + b,
+                    Opcode::Multiply => a * b,
+                    Opcode::LessThan => (a < b) as MemoryValue,
+                    _ => (a == b) as MemoryValue,
+                };
+                let program = generated(&params(&[a, b, 0]), &[a, b], &[4, written(2), 99]);
+                cases.push(Case::new(name, program, &[], Outcome::Halted(vec![result])));
+            }
+            Opcode::Input => {
+                let program = generated(&params(&[0]), &[], &[4, written(0), 99]);
+                cases.push(Case::new(name, program, &[5], Outcome::Halted(vec![5])));
+            }
+            Opcode::Output => {
+                let program = generated(&params(&[9]), &[9], &[99]);
+                cases.push(Case::new(name, program, &[], Outcome::Halted(vec![9])));
+            }
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+                // falls through to write 0, or jumps to write 1
+                let target = 8;
+                for &condition in &[0, 3] {
+                    let program = generated(
+                        &params(&[condition, target]),
+                        &[condition, target],
+                        &[104, 0, 99, 104, 1, 99],
+                    );
+                    let jumps = (condition != 0) == (opcode == Opcode::JumpIfTrue);
+                    cases.push(Case::new(
+                        format!("{} with condition {}", name, condition),
+                        program,
+                        &[],
+                        Outcome::Halted(vec![jumps as MemoryValue]),
+                    ));
+                }
+            }
+            Opcode::AdjustRelativeBase => {
+                // reads the marker relative to the new base
+                let (adjustment, marker) = (5, 77);
+                let offset = (DATA + 1) as MemoryValue - (RELATIVE_BASE + adjustment);
+                let program = generated(
+                    &params(&[adjustment]),
+                    &[adjustment, marker],
+                    &[204, offset, 99],
+                );
+                cases.push(Case::new(name, program, &[], Outcome::Halted(vec![marker])));
+            }
+            Opcode::Halt => {
+                cases.push(Case::new(name, vec![99], &[], Outcome::Halted(Vec::new())));
+                cases.push(Case::new(
+                    "HLT with an ignored mode".to_string(),
+                    vec![10099],
+                    &[],
+                    Outcome::Halted(Vec::new()),
+                ));
+            }
+        }
+    }
+
+    cases
+}
+
+/// Cases that should fault.
+fn faults() -> Vec<Case> {
+    let mut cases = Vec::new();
+
+    for &opcode in &[0, 10, 42, 98, -1] {
+        cases.push(Case::new(
+            format!("unknown opcode {}", opcode),
+            vec![opcode, 0, 0, 0],
+            &[],
+            Outcome::fault(0, None, &format!("unknown opcode {}", opcode)),
+        ));
+    }
+
+    for &opcode in &Opcode::ALL {
+        let mut program = vec![0; opcode.arity() + 2];
+        for parameter in 0..opcode.arity() {
+            program[0] = opcode.value() + 3 * 10i64.pow(parameter as u32 + 2);
+            cases.push(Case::new(
+                format!(
+                    "{} with mode 3 for parameter {}",
+                    opcode.mnemonic(),
+                    parameter
+                ),
+                program.clone(),
+                &[0],
+                Outcome::fault(0, Some(parameter), "unknown parameter mode 3"),
+            ));
+        }
+
+        let parameter = opcode.arity();
+        program[0] = opcode.value() + 10i64.pow(parameter as u32 + 2);
+        cases.push(
+            Case::new(
+                format!(
+                    "strict {} with a mode for a missing parameter",
+                    opcode.mnemonic()
+                ),
+                program.clone(),
+                &[0],
+                Outcome::fault(
+                    0,
+                    Some(parameter),
+                    &format!("mode 1 given for missing parameter {}", parameter),
+                ),
+            )
+            .strict(),
+        );
+
+        if let Some(parameter) = opcode.output_parameter() {
+            let modes = vec![Mode::Immediate; opcode.arity()];
+            program[0] = instruction_value(opcode, &modes);
+            cases.push(
+                Case::new(
+                    format!("strict {} writing in immediate mode", opcode.mnemonic()),
+                    program.clone(),
+                    &[0],
+                    Outcome::fault(0, Some(parameter), "write to an immediate mode parameter"),
+                )
+                .strict(),
+            );
+        }
+    }
+
+    let addresses: [(&str, &[MemoryValue], usize); 4] = [
+        ("negative position", &[4, -1, 99], 0),
+        ("negative relative address", &[204, -1, 99], 0),
+        ("negative write", &[1101, 1, 1, -5, 99], 2),
+        ("negative jump target", &[1105, 1, -1, 99], 1),
+    ];
+    for &(name, program, parameter) in &addresses {
+        let addr = program[parameter + 1];
+        cases.push(Case::new(
+            name.to_string(),
+            program.to_vec(),
+            &[],
+            Outcome::fault(0, Some(parameter), &format!("negative address {}", addr)),
+        ));
+    }
+
+    cases.push(Case::new(
+        "input exhausted".to_string(),
+        vec![3, 5, 3, 5, 99, 0],
+        &[1],
+        Outcome::fault(2, Some(0), "unexpected end of input"),
+    ));
+    cases.push(Case::new(
+        "infinite loop".to_string(),
+        vec![1105, 1, 0],
+        &[],
+        Outcome::fault(0, None, &format!("step limit of {} exceeded", MAX_STEPS)),
+    ));
+
+    let overflows: [(&str, &[MemoryValue]); 2] = [
+        ("overflowing add", &[1101, i64::MAX, 1, 0, 99]),
+        ("overflowing multiply", &[1102, i64::MIN, -1, 0, 99]),
+    ];
+    for &(name, program) in &overflows {
+        cases.push(Case {
+            overflows: true,
+            ..Case::new(
+                name.to_string(),
+                program.to_vec(),
+                &[],
+                Outcome::fault(0, None, "overflow"),
+            )
+        });
+    }
+
+    cases
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PagedMemory, SparseMemory};
+    use std::num::Wrapping;
+
+    fn assert_conforms<B: MemoryBackend>() {
+        let mismatches = check_conformance::<B>();
+        assert!(mismatches.is_empty(), "{}", mismatches.iter().join("\n"));
+    }
+
+    #[test]
+    fn backends() {
+        assert_conforms::<PagedMemory>();
+        assert_conforms::<SparseMemory>();
+        assert_conforms::<PagedMemory<Wrapping<i64>>>();
+        #[cfg(feature = "bigint")]
+        assert_conforms::<SparseMemory<num_bigint::BigInt>>();
+    }
+
+    #[test]
+    fn coverage() {
+        let cases = cases();
+        assert_eq!(
+            cases
+                .iter()
+                .filter(|case| case.name.starts_with("ADD ("))
+                .count(),
+            27
+        );
+        assert_eq!(
+            cases
+                .iter()
+                .filter(|case| case.name.starts_with("JT ("))
+                .count(),
+            18
+        );
+
+        let wrapping = cases
+            .iter()
+            .filter(|case| case.applies_to::<Wrapping<i64>>());
+        assert_eq!(wrapping.count(), cases.len() - 2);
+    }
+}
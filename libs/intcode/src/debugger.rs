@@ -0,0 +1,302 @@
+use crate::{
+    Instruction, IntCodeComputer, IntcodeError, Journal, JournalEntry, MemoryValue, Opcode, Status,
+};
+use std::collections::HashSet;
+
+/// Why a [`Debugger`] paused.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum Event {
+    /// A single instruction was executed.
+    Stepped,
+    /// The next instruction is at a breakpoint, or has an opcode with a
+    /// breakpoint on it, and hasn't been executed yet.
+    Breakpoint(usize),
+    /// An instruction wrote to a watched address.
+    Watchpoint {
+        addr: usize,
+        old: MemoryValue,
+        new: MemoryValue,
+    },
+    /// The machine halted, needs input, or wrote output.
+    Status(Status),
+}
+
+/// Runs an [`IntCodeComputer`] with breakpoints and watchpoints.
+///
+/// The machine can be inspected and modified through
+/// [`Debugger::computer_mut`] whenever the debugger is paused.
+#[derive(Clone, Debug)]
+pub struct Debugger {
+    computer: IntCodeComputer,
+    breakpoints: HashSet<usize>,
+    opcode_breakpoints: HashSet<Opcode>,
+    watchpoints: HashSet<usize>,
+    journal: Option<Journal>,
+}
+
+impl Debugger {
+    pub fn new(computer: IntCodeComputer) -> Self {
+        Debugger {
+            computer,
+            breakpoints: HashSet::new(),
+            opcode_breakpoints: HashSet::new(),
+            watchpoints: HashSet::new(),
+            journal: None,
+        }
+    }
+
+    pub fn computer(&self) -> &IntCodeComputer {
+        &self.computer
+    }
+
+    pub fn computer_mut(&mut self) -> &mut IntCodeComputer {
+        &mut self.computer
+    }
+
+    pub fn into_computer(self) -> IntCodeComputer {
+        self.computer
+    }
+
+    /// Pauses before executing the instruction at `addr`. Returns `false` if
+    /// there was already a breakpoint there.
+    pub fn add_breakpoint(&mut self, addr: usize) -> bool {
+        self.breakpoints.insert(addr)
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: usize) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    pub fn breakpoints(&self) -> &HashSet<usize> {
+        &self.breakpoints
+    }
+
+    /// Pauses before executing any instruction with the given opcode.
+    pub fn add_opcode_breakpoint(&mut self, opcode: Opcode) -> bool {
+        self.opcode_breakpoints.insert(opcode)
+    }
+
+    pub fn remove_opcode_breakpoint(&mut self, opcode: Opcode) -> bool {
+        self.opcode_breakpoints.remove(&opcode)
+    }
+
+    pub fn opcode_breakpoints(&self) -> &HashSet<Opcode> {
+        &self.opcode_breakpoints
+    }
+
+    /// Pauses after any instruction writes to `addr`, whether it's in the base
+    /// memory or additional memory.
+    pub fn add_watchpoint(&mut self, addr: usize) -> bool {
+        self.watchpoints.insert(addr)
+    }
+
+    pub fn remove_watchpoint(&mut self, addr: usize) -> bool {
+        self.watchpoints.remove(&addr)
+    }
+
+    pub fn watchpoints(&self) -> &HashSet<usize> {
+        &self.watchpoints
+    }
+
+    /// Records executed instructions in a [`Journal`], so they can be undone
+    /// with [`Debugger::step_back`]. Disabling the journal discards it.
+    pub fn set_journal(&mut self, enabled: bool) {
+        match (enabled, &self.journal) {
+            (true, None) => self.journal = Some(Journal::new()),
+            (false, Some(_)) => self.journal = None,
+            _ => {}
+        }
+    }
+
+    pub fn journal(&self) -> Option<&Journal> {
+        self.journal.as_ref()
+    }
+
+    /// Undoes the last instruction executed while the journal was enabled,
+    /// returning what it did, or `None` if there's nothing to undo.
+    pub fn step_back(&mut self) -> Option<JournalEntry> {
+        self.journal.as_mut()?.step_back(&mut self.computer)
+    }
+
+    /// Executes a single instruction, ignoring breakpoints.
+    pub fn step(&mut self) -> Result<Event, IntcodeError> {
+        let watched: Vec<_> = self
+            .computer
+            .next_writes()?
+            .into_iter()
+            .filter(|addr| self.watchpoints.contains(addr))
+            .map(|addr| (addr, self.computer.get(addr)))
+            .collect();
+        let status = match &mut self.journal {
+            Some(journal) => journal.step(&mut self.computer)?,
+            None => self.computer.step()?,
+        };
+
+        match (status, watched.first()) {
+            (Some(status), _) => Ok(Event::Status(status)),
+            (None, Some(&(addr, old))) => Ok(Event::Watchpoint {
+                addr,
+                old,
+                new: self.computer.get(addr),
+            }),
+            (None, None) => Ok(Event::Stepped),
+        }
+    }
+
+    /// Runs until a breakpoint or watchpoint is hit, or the machine halts,
+    /// needs input, or writes output. A breakpoint on the current instruction
+    /// doesn't pause execution, so resuming from a breakpoint makes progress.
+    pub fn resume(&mut self) -> Result<Event, IntcodeError> {
+        loop {
+            match self.step()? {
+                Event::Stepped => {}
+                event => return Ok(event),
+            }
+
+            let ip = self.computer.ip();
+            if self.breakpoints.contains(&ip) {
+                return Ok(Event::Breakpoint(ip));
+            }
+
+            if !self.opcode_breakpoints.is_empty() {
+                let opcode = Instruction::decode(self.computer.get(ip)).map(|i| i.opcode);
+                if matches!(opcode, Ok(opcode) if self.opcode_breakpoints.contains(&opcode)) {
+                    return Ok(Event::Breakpoint(ip));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assemble, Program};
+
+    fn debugger(source: &str) -> Debugger {
+        let program = Program::from(assemble(source).unwrap());
+        Debugger::new(IntCodeComputer::new(&program))
+    }
+
+    const COUNTDOWN: &str = "
+        loop:   OUT [counter]
+                ADD [counter], #-1 -> [counter]
+                JT [counter], #loop
+                ADD #1, #1 -> [1000]
+                HLT
+        counter: DATA 2
+    ";
+
+    #[test]
+    fn step() {
+        let mut debugger = debugger(COUNTDOWN);
+        assert_eq!(debugger.step().unwrap(), Event::Status(Status::Output(2)));
+        assert_eq!(debugger.step().unwrap(), Event::Stepped);
+        assert_eq!(debugger.computer().ip(), 6);
+    }
+
+    #[test]
+    fn breakpoints() {
+        let mut debugger = debugger(COUNTDOWN);
+        debugger.add_breakpoint(6);
+        assert_eq!(debugger.resume().unwrap(), Event::Status(Status::Output(2)));
+        assert_eq!(debugger.resume().unwrap(), Event::Breakpoint(6));
+        assert_eq!(debugger.resume().unwrap(), Event::Status(Status::Output(1)));
+        assert_eq!(debugger.resume().unwrap(), Event::Breakpoint(6));
+
+        debugger.remove_breakpoint(6);
+        debugger.add_opcode_breakpoint(Opcode::Halt);
+        assert_eq!(debugger.resume().unwrap(), Event::Breakpoint(13));
+        assert_eq!(debugger.resume().unwrap(), Event::Status(Status::Halted));
+    }
+
+    #[test]
+    fn watchpoints() {
+        let mut debugger = debugger(COUNTDOWN);
+        debugger.add_watchpoint(14);
+        debugger.add_watchpoint(1000);
+        assert_eq!(debugger.resume().unwrap(), Event::Status(Status::Output(2)));
+        assert_eq!(
+            debugger.resume().unwrap(),
+            Event::Watchpoint {
+                addr: 14,
+                old: 2,
+                new: 1
+            }
+        );
+        assert_eq!(debugger.resume().unwrap(), Event::Status(Status::Output(1)));
+        assert_eq!(
+            debugger.resume().unwrap(),
+            Event::Watchpoint {
+                addr: 14,
+                old: 1,
+                new: 0
+            }
+        );
+        assert_eq!(
+            debugger.resume().unwrap(),
+            Event::Watchpoint {
+                addr: 1000,
+                old: 0,
+                new: 2
+            }
+        );
+        assert_eq!(debugger.resume().unwrap(), Event::Status(Status::Halted));
+    }
+
+    #[test]
+    fn poke_while_paused() {
+        let mut debugger = debugger(COUNTDOWN);
+        debugger.add_breakpoint(6);
+        debugger.resume().unwrap();
+        assert_eq!(debugger.resume().unwrap(), Event::Breakpoint(6));
+
+        // skip the rest of the countdown
+        debugger.computer_mut().set(14, 0);
+        assert_eq!(debugger.resume().unwrap(), Event::Status(Status::Halted));
+        assert_eq!(debugger.computer().get(1000), 2);
+    }
+
+    #[test]
+    fn step_back() {
+        let mut debugger = debugger(COUNTDOWN);
+        assert_eq!(debugger.step_back(), None);
+
+        debugger.set_journal(true);
+        debugger.add_breakpoint(6);
+        debugger.resume().unwrap();
+        assert_eq!(debugger.resume().unwrap(), Event::Breakpoint(6));
+        assert_eq!(debugger.computer().get(14), 1);
+        assert_eq!(debugger.journal().unwrap().last_write(14).unwrap().ip, 2);
+
+        assert_eq!(debugger.step_back().unwrap().ip, 2);
+        assert_eq!(debugger.computer().get(14), 2);
+        assert_eq!(debugger.step_back().unwrap().ip, 0);
+        assert_eq!(debugger.step_back(), None);
+        assert_eq!(debugger.resume().unwrap(), Event::Status(Status::Output(2)));
+
+        debugger.set_journal(false);
+        debugger.step().unwrap();
+        assert_eq!(debugger.step_back(), None);
+    }
+
+    #[test]
+    fn blocked_input_is_not_a_write() {
+        let mut debugger = debugger("IN -> [5]\nHLT");
+        debugger.add_watchpoint(5);
+        assert_eq!(
+            debugger.resume().unwrap(),
+            Event::Status(Status::NeedsInput)
+        );
+
+        debugger.computer_mut().provide_input(9);
+        assert_eq!(
+            debugger.resume().unwrap(),
+            Event::Watchpoint {
+                addr: 5,
+                old: 0,
+                new: 9
+            }
+        );
+    }
+}
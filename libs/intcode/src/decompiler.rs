@@ -0,0 +1,651 @@
+use crate::{
+    BasicBlock, BlockEnd, ControlFlowGraph, EdgeKind, Instruction, Item, MemoryValue, Mode, Opcode,
+    Operand,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Where control goes in the code being emitted.
+#[derive(Clone, Copy)]
+struct Context {
+    /// Where control goes after falling off the end of the region.
+    follow: usize,
+    /// Where `break` goes, in a loop.
+    break_to: Option<usize>,
+    /// Where `continue` goes, in a `loop`.
+    continue_to: Option<usize>,
+    /// The head of the loop being emitted, so it isn't emitted again.
+    loop_head: Option<usize>,
+    /// The block whose jump is the condition of the `do` loop being emitted.
+    loop_tail: Option<usize>,
+}
+
+enum Output {
+    Label(usize),
+    Line(usize, String),
+}
+
+/// The blocks of a function: those reachable from its entry without following
+/// calls.
+struct Function<'a> {
+    name: String,
+    blocks: BTreeMap<usize, &'a BasicBlock>,
+    /// The end of the last block that jumps back to each loop head.
+    loops: BTreeMap<usize, usize>,
+}
+
+fn function_name(entry: usize) -> String {
+    if entry == 0 {
+        "main".to_string()
+    } else {
+        format!("fn_{}", entry)
+    }
+}
+
+impl<'a> Function<'a> {
+    fn new(graph: &'a ControlFlowGraph, entry: usize) -> Self {
+        let mut blocks = BTreeMap::new();
+        let mut queue = vec![entry];
+        while let Some(start) = queue.pop() {
+            match graph.blocks.iter().find(|block| block.start == start) {
+                Some(block) if !blocks.contains_key(&start) => {
+                    blocks.insert(start, block);
+                    let edges = block
+                        .edges
+                        .iter()
+                        .filter(|edge| edge.kind != EdgeKind::Call);
+                    queue.extend(edges.map(|edge| edge.to));
+                }
+                _ => {}
+            }
+        }
+
+        let mut loops = BTreeMap::new();
+        for block in blocks.values() {
+            for edge in &block.edges {
+                if edge.kind != EdgeKind::Call
+                    && edge.to <= block.start
+                    && blocks.contains_key(&edge.to)
+                {
+                    let end = loops.entry(edge.to).or_insert(0);
+                    *end = block.range().end.max(*end);
+                }
+            }
+        }
+
+        Function {
+            name: function_name(entry),
+            blocks,
+            loops,
+        }
+    }
+}
+
+fn operand(operand: &Operand) -> String {
+    match operand.mode {
+        Mode::Position => format!("v{}", operand.value),
+        Mode::Immediate => operand.value.to_string(),
+        Mode::Relative => format!("rb[{}]", operand.value),
+    }
+}
+
+/// Whether a jump instruction's condition is a constant that never jumps.
+fn never_jumps(instruction: Instruction, operands: &[Operand]) -> bool {
+    operands[0].mode == Mode::Immediate
+        && (operands[0].value != 0) != (instruction.opcode == Opcode::JumpIfTrue)
+}
+
+fn is_jump(instruction: Instruction) -> bool {
+    matches!(instruction.opcode, Opcode::JumpIfTrue | Opcode::JumpIfFalse)
+}
+
+/// The statement for an instruction that doesn't affect control flow.
+fn statement(instruction: Instruction, operands: &[Operand]) -> Option<String> {
+    let values: Vec<String> = operands.iter().map(operand).collect();
+    let is = |index: usize, value: MemoryValue| {
+        operands[index].mode == Mode::Immediate && operands[index].value == value
+    };
+
+    let statement = match instruction.opcode {
+        Opcode::Add if is(0, 0) => format!("{} = {};", values[2], values[1]),
+        Opcode::Add if is(1, 0) => format!("{} = {};", values[2], values[0]),
+        Opcode::Add if operands[1].mode == Mode::Immediate && operands[1].value < 0 => format!(
+            "{} = {} - {};",
+            values[2],
+            values[0],
+            -(operands[1].value as i128)
+        ),
+        Opcode::Add => format!("{} = {} + {};", values[2], values[0], values[1]),
+        Opcode::Multiply if is(0, 1) => format!("{} = {};", values[2], values[1]),
+        Opcode::Multiply if is(1, 1) => format!("{} = {};", values[2], values[0]),
+        Opcode::Multiply => format!("{} = {} * {};", values[2], values[0], values[1]),
+        Opcode::LessThan => format!("{} = {} < {};", values[2], values[0], values[1]),
+        Opcode::Equals => format!("{} = {} == {};", values[2], values[0], values[1]),
+        Opcode::Input => format!("{} = input();", values[0]),
+        Opcode::Output => format!("output({});", values[0]),
+        Opcode::AdjustRelativeBase
+            if operands[0].mode == Mode::Immediate && operands[0].value < 0 =>
+        {
+            format!("rb -= {};", -(operands[0].value as i128))
+        }
+        Opcode::AdjustRelativeBase => format!("rb += {};", values[0]),
+        Opcode::JumpIfTrue | Opcode::JumpIfFalse | Opcode::Halt => return None,
+    };
+
+    Some(statement)
+}
+
+/// The condition under which a jump instruction jumps, or doesn't if
+/// `negate` is set.
+fn condition(instruction: Instruction, operands: &[Operand], negate: bool) -> String {
+    let jumps_if_true = instruction.opcode == Opcode::JumpIfTrue;
+    let comparison = if jumps_if_true != negate { "!=" } else { "==" };
+    format!("{} {} 0", operand(&operands[0]), comparison)
+}
+
+struct Decompiler<'a> {
+    function: &'a Function<'a>,
+    output: Vec<Output>,
+    /// The blocks emitted so far.
+    emitted: BTreeSet<usize>,
+    gotos: BTreeSet<usize>,
+}
+
+impl<'a> Decompiler<'a> {
+    fn line(&mut self, indent: usize, line: String) {
+        self.output.push(Output::Line(indent, line));
+    }
+
+    /// The statement that sends control to `target`, where falling through
+    /// would go to `natural`.
+    fn jump(&mut self, target: usize, natural: usize, context: Context) -> Option<String> {
+        if target == natural {
+            None
+        } else if Some(target) == context.continue_to {
+            Some("continue;".to_string())
+        } else if Some(target) == context.break_to {
+            Some("break;".to_string())
+        } else {
+            self.gotos.insert(target);
+            Some(format!("goto label_{};", target))
+        }
+    }
+
+    /// Where control goes after the code for a block ending at `end`, in the
+    /// region ending at `to`.
+    fn natural(&self, end: usize, to: usize, context: Context) -> usize {
+        match self.function.blocks.range(end..to.max(end)).next() {
+            Some((&start, _)) => start,
+            None => context.follow,
+        }
+    }
+
+    /// Whether code emitted for the region `[from, to)` reaches `target` by
+    /// falling off its end or through the block at `target`.
+    fn reaches(&self, target: usize, to: usize, context: Context) -> bool {
+        (target < to && self.function.blocks.contains_key(&target))
+            || (target == to && context.follow == to)
+    }
+
+    /// Emits the blocks of the function in `[from, to)`.
+    fn region(&mut self, from: usize, to: usize, indent: usize, context: Context) {
+        let function = self.function;
+        let mut at = from;
+        while let Some((&start, &block)) = function.blocks.range(at..to).next() {
+            self.output.push(Output::Label(start));
+            self.emitted.insert(start);
+
+            // loops
+            match function.loops.get(&start) {
+                Some(&end) if end <= to && context.loop_head != Some(start) => {
+                    let tail = function.blocks.range(..end).next_back().map(|(_, &b)| b);
+                    let do_while = tail.and_then(|tail| match tail.lines.last() {
+                        Some(line) => match &line.item {
+                            Item::Instruction {
+                                instruction,
+                                operands,
+                            } if is_jump(*instruction)
+                                && tail.edges.iter().any(|edge| {
+                                    edge.to == start && edge.kind == EdgeKind::Jump
+                                })
+                                && tail.edges.iter().any(|edge| edge.kind == EdgeKind::Next) =>
+                            {
+                                Some((tail.start, condition(*instruction, operands, false)))
+                            }
+                            _ => None,
+                        },
+                        None => None,
+                    });
+
+                    let body = Context {
+                        follow: start,
+                        break_to: Some(end),
+                        continue_to: Some(start),
+                        loop_head: Some(start),
+                        loop_tail: None,
+                    };
+                    match do_while {
+                        Some((tail, condition)) => {
+                            self.line(indent, "do {".to_string());
+                            self.region(
+                                start,
+                                end,
+                                indent + 1,
+                                Context {
+                                    follow: end,
+                                    continue_to: None,
+                                    loop_tail: Some(tail),
+                                    ..body
+                                },
+                            );
+                            self.line(indent, format!("}} while ({});", condition));
+                        }
+                        None => {
+                            self.line(indent, "loop {".to_string());
+                            self.region(start, end, indent + 1, body);
+                            self.line(indent, "}".to_string());
+                        }
+                    }
+                    at = end;
+                    continue;
+                }
+                _ => {}
+            }
+
+            // blocks at invalid addresses are empty
+            at = self.block(block, to, indent, context).max(start + 1);
+        }
+    }
+
+    /// Emits a block, returning where to continue emitting the region from.
+    fn block(&mut self, block: &BasicBlock, to: usize, indent: usize, context: Context) -> usize {
+        let end = block.range().end;
+        let natural = self.natural(end, to, context);
+
+        let is_call = block.edges.iter().any(|edge| edge.kind == EdgeKind::Call);
+        let mut lines: Vec<(Instruction, &[Operand])> = block
+            .lines
+            .iter()
+            .filter_map(|line| match &line.item {
+                Item::Instruction {
+                    instruction,
+                    operands,
+                } => Some((*instruction, &operands[..])),
+                Item::Data(_) => None,
+            })
+            .collect();
+
+        // the last instruction decides where the block goes
+        let terminator = match lines.last() {
+            Some(&(instruction, operands))
+                if (is_jump(instruction) && !never_jumps(instruction, operands))
+                    || instruction.opcode == Opcode::Halt =>
+            {
+                lines.pop()
+            }
+            _ => None,
+        };
+        if is_call {
+            // the return address pushed before the call
+            lines.pop();
+        }
+
+        for (instruction, operands) in lines {
+            if let Some(statement) = statement(instruction, operands) {
+                self.line(indent, statement);
+            }
+        }
+
+        if context.loop_tail == Some(block.start) {
+            return end;
+        }
+
+        let next = block
+            .edges
+            .iter()
+            .find(|edge| matches!(edge.kind, EdgeKind::Next | EdgeKind::Return))
+            .map(|edge| edge.to);
+        let jump = block
+            .edges
+            .iter()
+            .find(|edge| matches!(edge.kind, EdgeKind::Jump | EdgeKind::Call));
+
+        match (block.end, terminator) {
+            (BlockEnd::Halt, _) => self.line(indent, "halt;".to_string()),
+            (BlockEnd::Invalid, _) => {
+                let addr = block.lines.last().map_or(block.start, |_| end);
+                self.line(indent, format!("invalid; // at {}", addr));
+            }
+            (BlockEnd::Return, Some((instruction, operands))) if next.is_some() => {
+                let condition = condition(instruction, operands, false);
+                self.line(indent, format!("if ({}) return;", condition));
+            }
+            (BlockEnd::Return, _) => self.line(indent, "return;".to_string()),
+            (BlockEnd::IndirectJump, Some((instruction, operands))) => {
+                let target = operand(&operands[1]);
+                match next {
+                    Some(_) => {
+                        let condition = condition(instruction, operands, false);
+                        self.line(indent, format!("if ({}) goto *{};", condition, target));
+                    }
+                    None => self.line(indent, format!("goto *{};", target)),
+                }
+            }
+            (BlockEnd::IndirectJump, None) => {}
+            (BlockEnd::Branch, _) => match (jump, next, terminator) {
+                (Some(jump), _, _) if jump.kind == EdgeKind::Call => {
+                    self.line(indent, format!("{}();", function_name(jump.to)));
+                }
+                (Some(jump), None, _) => {
+                    if let Some(statement) = self.jump(jump.to, natural, context) {
+                        self.line(indent, statement);
+                    }
+                    return end;
+                }
+                (Some(jump), Some(next), Some((instruction, operands))) => {
+                    return self.branch(
+                        block,
+                        jump.to,
+                        next,
+                        (instruction, operands),
+                        to,
+                        indent,
+                        context,
+                    );
+                }
+                _ => {}
+            },
+        }
+
+        if let Some(statement) = next.and_then(|next| self.jump(next, natural, context)) {
+            self.line(indent, statement);
+        }
+        end
+    }
+
+    /// Emits a conditional jump to `target` that otherwise continues at
+    /// `next`, structuring it as an `if` where possible.
+    #[allow(clippy::too_many_arguments)]
+    fn branch(
+        &mut self,
+        block: &BasicBlock,
+        target: usize,
+        next: usize,
+        (instruction, operands): (Instruction, &[Operand]),
+        to: usize,
+        indent: usize,
+        context: Context,
+    ) -> usize {
+        let end = block.range().end;
+        if target > next && next == end && self.reaches(target, to, context) {
+            let then_follow = if target == to { context.follow } else { target };
+            let condition = condition(instruction, operands, true);
+
+            // a then branch that ends by jumping past the else branch
+            let last = self.function.blocks.range(next..target).next_back();
+            let skip = last.and_then(|(_, last)| match &last.edges[..] {
+                [edge] if edge.kind == EdgeKind::Jump && last.end == BlockEnd::Branch => {
+                    Some(edge.to)
+                }
+                _ => None,
+            });
+            if let Some(skip) =
+                skip.filter(|&skip| skip > target && self.reaches(skip, to, context))
+            {
+                let else_follow = if skip == to { context.follow } else { skip };
+                self.line(indent, format!("if ({}) {{", condition));
+                self.region(
+                    next,
+                    target,
+                    indent + 1,
+                    Context {
+                        follow: else_follow,
+                        ..context
+                    },
+                );
+                self.line(indent, "} else {".to_string());
+                self.region(
+                    target,
+                    skip,
+                    indent + 1,
+                    Context {
+                        follow: else_follow,
+                        ..context
+                    },
+                );
+                self.line(indent, "}".to_string());
+                return skip;
+            }
+
+            self.line(indent, format!("if ({}) {{", condition));
+            self.region(
+                next,
+                target,
+                indent + 1,
+                Context {
+                    follow: then_follow,
+                    ..context
+                },
+            );
+            self.line(indent, "}".to_string());
+            return target;
+        }
+
+        let natural = self.natural(end, to, context);
+        let condition = condition(instruction, operands, false);
+        // a jump to the next block needs no statement
+        if let Some(statement) = self.jump(target, next, context) {
+            self.line(indent, format!("if ({}) {}", condition, statement));
+        }
+        if let Some(statement) = self.jump(next, natural, context) {
+            self.line(indent, statement);
+        }
+        end
+    }
+
+    /// Emits the whole function.
+    fn function(&mut self) {
+        let context = Context {
+            follow: usize::MAX,
+            break_to: None,
+            continue_to: None,
+            loop_head: None,
+            loop_tail: None,
+        };
+        self.region(0, usize::MAX, 1, context);
+
+        // blocks overlapping others, reached by jumping into the middle of an
+        // instruction, are emitted after the rest of the function
+        while let Some(&start) = self.gotos.difference(&self.emitted).next() {
+            self.output.push(Output::Label(start));
+            self.emitted.insert(start);
+            if let Some(&block) = self.function.blocks.get(&start) {
+                self.block(block, start, 1, context);
+            }
+        }
+    }
+
+    fn render(self, text: &mut String) {
+        for output in self.output {
+            match output {
+                Output::Label(addr) if self.gotos.contains(&addr) => {
+                    text.push_str(&format!("label_{}:\n", addr));
+                }
+                Output::Label(_) => {}
+                Output::Line(indent, line) => {
+                    text.push_str(&format!("{}{}\n", "    ".repeat(indent), line));
+                }
+            }
+        }
+    }
+}
+
+/// Decompiles a program into structured pseudo-code.
+///
+/// Each function found by [`ControlFlowGraph`] becomes a `fn`, with `main`
+/// starting at address 0. Values at constant addresses are shown as variables
+/// named after their address, like `v63`, and values relative to the relative
+/// base as `rb[offset]`. Jumps become `if`, `loop` and `do`/`while` statements
+/// where possible and `goto` otherwise. `loop` repeats until a `break`, and
+/// `continue` goes back to the start of the `loop`.
+pub fn decompile(memory: &[MemoryValue]) -> String {
+    let graph = ControlFlowGraph::new(memory);
+    let mut entries: BTreeSet<usize> = graph
+        .blocks
+        .iter()
+        .flat_map(|block| &block.edges)
+        .filter(|edge| edge.kind == EdgeKind::Call)
+        .map(|edge| edge.to)
+        .collect();
+    entries.insert(0);
+
+    let mut text = String::new();
+    for write in &graph.self_modifying_writes {
+        text.push_str(&format!(
+            "// warning: the instruction at {} modifies code at {}\n",
+            write.addr, write.target
+        ));
+    }
+
+    for entry in entries {
+        let function = Function::new(&graph, entry);
+        let mut decompiler = Decompiler {
+            function: &function,
+            output: Vec::new(),
+            emitted: BTreeSet::new(),
+            gotos: BTreeSet::new(),
+        };
+        decompiler.function();
+
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&format!("fn {}() {{\n", function.name));
+        decompiler.render(&mut text);
+        text.push_str("}\n");
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble;
+
+    fn decompiled(source: &str) -> String {
+        decompile(&assemble(source).unwrap())
+    }
+
+    #[test]
+    fn structures() {
+        let text = decompiled(
+            "
+                    IN -> [n]
+            loop:   LT [n], #10 -> [small]
+                    JF [small], #big
+                    OUT [n]
+                    JT #1, #next
+            big:    MUL [n], #2 -> [big_n]
+                    OUT [big_n]
+            next:   ADD [n], #-1 -> [n]
+                    JT [n], #loop
+            read:   IN -> [n]
+                    EQ [n], #0 -> [small]
+                    JT [small], #done
+                    OUT [n]
+                    JT #1, #read
+            done:   HLT
+            n:      DATA 0
+            small:  DATA 0
+            big_n:  DATA 0
+            ",
+        );
+        let expected = "\
+fn main() {
+    v42 = input();
+    do {
+        v43 = v42 < 10;
+        if (v43 != 0) {
+            output(v42);
+        } else {
+            v44 = v42 * 2;
+            output(v44);
+        }
+        v42 = v42 - 1;
+    } while (v42 != 0);
+    loop {
+        v42 = input();
+        v43 = v42 == 0;
+        if (v43 != 0) break;
+        output(v42);
+    }
+    halt;
+}
+";
+        assert_eq!(text, expected);
+    }
+
+    #[test]
+    fn functions() {
+        let text = decompiled(
+            "
+                    ARB #stack
+                    ADD #0, #after -> [rb+0]
+                    JT #1, #double
+            after:  OUT [rb+1]
+                    HLT
+            double: MUL [rb+1], #2 -> [rb+1]
+                    JF #0, [rb+0]
+            stack:  DATA 0, 0
+            ",
+        );
+        let expected = "\
+fn main() {
+    rb += 19;
+    fn_12();
+    output(rb[1]);
+    halt;
+}
+
+fn fn_12() {
+    rb[1] = rb[1] * 2;
+    return;
+}
+";
+        assert_eq!(text, expected);
+    }
+
+    #[test]
+    fn unstructured() {
+        let text = decompiled(
+            "
+                    IN -> [n]
+                    JT [n], #inner
+            top:    OUT #1
+            inner:  OUT #2
+                    ADD [n], #-1 -> [n]
+                    JT [n], #top
+                    ADD #99, #0 -> [end]
+            end:    DATA 0
+            n:      DATA 0
+            ",
+        );
+        let expected = "\
+// warning: the instruction at 16 modifies code at 20
+
+fn main() {
+    v21 = input();
+    if (v21 == 0) {
+label_5:
+        output(1);
+    }
+    output(2);
+    v21 = v21 - 1;
+    if (v21 != 0) goto label_5;
+    v20 = 99;
+    invalid; // at 20
+}
+";
+        assert_eq!(text, expected);
+    }
+}
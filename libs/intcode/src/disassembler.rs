@@ -0,0 +1,210 @@
+use crate::{Instruction, MemoryValue, Mode};
+use std::fmt::{self, Display, Formatter};
+
+const DATA_PER_LINE: usize = 8;
+
+/// A parameter of a disassembled instruction.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct Operand {
+    pub mode: Mode,
+    pub value: MemoryValue,
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.mode {
+            Mode::Position => write!(f, "[{}]", self.value),
+            Mode::Immediate => write!(f, "#{}", self.value),
+            Mode::Relative if self.value < 0 => write!(f, "[rb{}]", self.value),
+            Mode::Relative => write!(f, "[rb+{}]", self.value),
+        }
+    }
+}
+
+/// The contents of a disassembled line.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub enum Item {
+    Instruction {
+        instruction: Instruction,
+        operands: Vec<Operand>,
+    },
+    /// Values that don't decode to a complete instruction.
+    Data(Vec<MemoryValue>),
+}
+
+impl Item {
+    /// The number of memory cells the item occupies.
+    pub fn len(&self) -> usize {
+        match self {
+            Item::Instruction { operands, .. } => operands.len() + 1,
+            Item::Data(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Display for Item {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Item::Instruction {
+                instruction,
+                operands,
+            } => {
+                write!(f, "{}", instruction.opcode)?;
+
+                let output = instruction.opcode.output_parameter();
+                let inputs = operands
+                    .iter()
+                    .enumerate()
+                    .filter(|&(i, _)| Some(i) != output);
+                for (n, (_, operand)) in inputs.enumerate() {
+                    let separator = if n == 0 { " " } else { ", " };
+                    write!(f, "{}{}", separator, operand)?;
+                }
+
+                if let Some(operand) = output.and_then(|i| operands.get(i)) {
+                    write!(f, " -> {}", operand)?;
+                }
+
+                Ok(())
+            }
+            Item::Data(values) => {
+                write!(f, "DATA")?;
+                for (n, value) in values.iter().enumerate() {
+                    let separator = if n == 0 { " " } else { ", " };
+                    write!(f, "{}{}", separator, value)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A line of a disassembly listing.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct Line {
+    pub addr: usize,
+    pub item: Item,
+}
+
+impl Display for Line {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}: {}", self.addr, self.item)
+    }
+}
+
+/// Disassembles memory into a listing, starting from address 0.
+///
+/// Values that aren't valid instructions, instructions whose parameters run
+/// past the end of memory, and instructions with mode digits that the opcode
+/// doesn't use are listed as data, so the listing can be reassembled exactly.
+pub fn disassemble(memory: &[MemoryValue]) -> Vec<Line> {
+    let mut lines: Vec<Line> = Vec::new();
+    let mut addr = 0;
+    while addr < memory.len() {
+        let instruction = Instruction::decode(memory[addr])
+            .ok()
+            .filter(|instruction| instruction.encode() == memory[addr])
+            .filter(|instruction| addr + instruction.size() <= memory.len());
+
+        match instruction {
+            Some(instruction) => {
+                let operands = memory[addr + 1..addr + instruction.size()]
+                    .iter()
+                    .zip(&instruction.modes)
+                    .map(|(&value, &mode)| Operand { mode, value })
+                    .collect();
+
+                lines.push(Line {
+                    addr,
+                    item: Item::Instruction {
+                        instruction,
+                        operands,
+                    },
+                });
+                addr += instruction.size();
+            }
+            None => {
+                match lines.last_mut() {
+                    Some(Line {
+                        item: Item::Data(values),
+                        ..
+                    }) if values.len() < DATA_PER_LINE => values.push(memory[addr]),
+                    _ => lines.push(Line {
+                        addr,
+                        item: Item::Data(vec![memory[addr]]),
+                    }),
+                }
+                addr += 1;
+            }
+        }
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listing(memory: &[MemoryValue]) -> Vec<String> {
+        disassemble(memory)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn instructions() {
+        assert_eq!(
+            listing(&[
+                21101, 3, 5, 104, 3, 100, 4, 101, 2, 15, 16, 17, 1005, 7, 0, 1206, -2, 12, 109, -7,
+                203, -1, 99
+            ]),
+            [
+                "0000: ADD #3, #5 -> [rb+104]",
+                "0004: IN -> [100]",
+                "0006: OUT [101]",
+                "0008: MUL [15], [16] -> [17]",
+                "0012: JT [7], #0",
+                "0015: JF [rb-2], #12",
+                "0018: ARB #-7",
+                "0020: IN -> [rb-1]",
+                "0022: HLT",
+            ]
+        );
+    }
+
+    #[test]
+    fn data() {
+        assert_eq!(
+            listing(&[99, 0, -5, 42, 7, 8, 9, 10, 11, 12, 13, 1, 2]),
+            [
+                "0000: HLT",
+                "0001: DATA 0, -5, 42",
+                "0004: LT [8], [9] -> [10]",
+                "0008: DATA 11, 12, 13, 1, 2",
+            ]
+        );
+
+        // operands are missing, so the instruction is data
+        assert_eq!(listing(&[1101, 1]), ["0000: DATA 1101, 1"]);
+
+        // unused mode digits
+        assert_eq!(listing(&[1104, 1]), ["0000: DATA 1104, 1"]);
+    }
+
+    #[test]
+    fn long_data() {
+        let lines = disassemble(&[0; 20]);
+        assert_eq!(
+            lines.iter().map(|line| line.addr).collect::<Vec<_>>(),
+            [0, 8, 16]
+        );
+        assert_eq!(lines[2].item.len(), 4);
+    }
+}
@@ -0,0 +1,252 @@
+use crate::MemoryValue;
+use std::{
+    fmt::{self, Display, Formatter},
+    num::ParseIntError,
+    str::Utf8Error,
+};
+
+/// What went wrong while executing an instruction.
+#[derive(Debug)]
+pub enum ErrorKind {
+    /// The last two digits of the instruction aren't a known opcode.
+    UnknownOpcode(MemoryValue),
+    /// The mode digit of the parameter with the given zero-based index isn't a
+    /// known mode.
+    UnknownMode { parameter: usize, mode: MemoryValue },
+    /// A mode digit was given for a parameter the instruction doesn't take.
+    /// Only raised with [`Validation::Strict`](crate::Validation::Strict).
+    UnexpectedMode { parameter: usize, mode: MemoryValue },
+    /// An arithmetic result doesn't fit in the machine's word, or a value used
+    /// as an address, opcode or relative base doesn't fit in an `i64`.
+    Overflow,
+    /// A parameter or jump target refers to a negative address.
+    NegativeAddress(MemoryValue),
+    /// An instruction tried to write to an immediate mode parameter. Only
+    /// raised with [`Validation::Strict`](crate::Validation::Strict).
+    WriteToImmediate,
+    /// The machine has executed as many instructions as its
+    /// [`Limits`](crate::Limits) allow.
+    StepLimitExceeded(u64),
+    /// The machine ran past the deadline set in its [`Limits`](crate::Limits).
+    DeadlineExceeded,
+    /// A parameter or jump target refers to an address at or past the maximum
+    /// address set in the machine's [`Limits`](crate::Limits).
+    AddressOutOfRange(usize),
+    /// A write needed more memory than the machine's [`Limits`](crate::Limits)
+    /// allow.
+    MemoryLimitExceeded(usize),
+    /// An input instruction ran with no input left to read.
+    InputExhausted,
+    /// A [`SymbolicComputer`](crate::SymbolicComputer) needed a value that
+    /// depends on a symbol as an opcode, address, relative base offset or jump
+    /// condition.
+    SymbolicValue,
+    /// The input or output callback passed to
+    /// [`IntCodeComputer::run_with`](crate::IntCodeComputer::run_with) failed.
+    Callback(anyhow::Error),
+    /// The handler of a custom opcode registered with
+    /// [`IntCodeComputer::register_opcode`](crate::IntCodeComputer::register_opcode)
+    /// failed.
+    Extension(anyhow::Error),
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::UnknownOpcode(opcode) => write!(f, "unknown opcode {}", opcode),
+            ErrorKind::UnknownMode { mode, .. } => write!(f, "unknown parameter mode {}", mode),
+            ErrorKind::UnexpectedMode { parameter, mode } => {
+                write!(f, "mode {} given for missing parameter {}", mode, parameter)
+            }
+            ErrorKind::Overflow => f.write_str("overflow"),
+            ErrorKind::NegativeAddress(addr) => write!(f, "negative address {}", addr),
+            ErrorKind::WriteToImmediate => f.write_str("write to an immediate mode parameter"),
+            ErrorKind::StepLimitExceeded(steps) => write!(f, "step limit of {} exceeded", steps),
+            ErrorKind::DeadlineExceeded => f.write_str("deadline exceeded"),
+            ErrorKind::AddressOutOfRange(addr) => write!(f, "address {} out of range", addr),
+            ErrorKind::MemoryLimitExceeded(cells) => {
+                write!(f, "memory limit of {} cells exceeded", cells)
+            }
+            ErrorKind::InputExhausted => f.write_str("unexpected end of input"),
+            ErrorKind::SymbolicValue => f.write_str("value depends on a symbol"),
+            ErrorKind::Callback(_) => f.write_str("input or output callback failed"),
+            ErrorKind::Extension(_) => f.write_str("custom opcode failed"),
+        }
+    }
+}
+
+impl std::error::Error for ErrorKind {}
+
+/// An error raised by an [`IntCodeComputer`](crate::IntCodeComputer), along
+/// with where in the program it happened.
+#[derive(Debug)]
+pub struct IntcodeError {
+    /// The address of the faulting instruction.
+    pub ip: usize,
+    /// The raw value of the faulting instruction, saturated to fit in an `i64`.
+    pub instruction: MemoryValue,
+    /// The zero-based index of the faulting parameter, if the fault is caused
+    /// by one.
+    pub parameter: Option<usize>,
+    pub kind: ErrorKind,
+}
+
+impl IntcodeError {
+    /// Whether the error was caused by the program itself, rather than by a
+    /// callback the machine was run with.
+    pub fn is_fault(&self) -> bool {
+        !matches!(self.kind, ErrorKind::Callback(_))
+    }
+}
+
+impl Display for IntcodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at {} (instruction {}",
+            self.kind, self.ip, self.instruction
+        )?;
+        if let Some(parameter) = self.parameter {
+            write!(f, ", parameter {}", parameter)?;
+        }
+        f.write_str(")")
+    }
+}
+
+impl std::error::Error for IntcodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::Callback(error) | ErrorKind::Extension(error) => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// An error running a machine with a [`Tracer`](crate::Tracer).
+#[derive(Debug)]
+pub enum TraceError {
+    /// The machine faulted or a callback it was run with failed.
+    Machine(IntcodeError),
+    /// The [`TraceSink`](crate::TraceSink) failed to record an entry.
+    Sink(anyhow::Error),
+}
+
+impl TraceError {
+    /// Whether the error was caused by the program itself.
+    pub fn is_fault(&self) -> bool {
+        matches!(self, TraceError::Machine(error) if error.is_fault())
+    }
+}
+
+impl Display for TraceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceError::Machine(error) => Display::fmt(error, f),
+            TraceError::Sink(_) => f.write_str("failed to record trace entry"),
+        }
+    }
+}
+
+impl std::error::Error for TraceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TraceError::Machine(error) => error.source(),
+            TraceError::Sink(error) => Some(error.as_ref()),
+        }
+    }
+}
+
+impl From<IntcodeError> for TraceError {
+    fn from(error: IntcodeError) -> Self {
+        TraceError::Machine(error)
+    }
+}
+
+/// An error running a [`Network`](crate::Network) or
+/// [`PacketNetwork`](crate::PacketNetwork).
+#[derive(Debug)]
+pub enum NetworkError {
+    /// The machine with the given id faulted.
+    Machine { id: usize, error: IntcodeError },
+    /// A packet was sent to an address with no machine.
+    UnknownAddress(MemoryValue),
+}
+
+impl Display for NetworkError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkError::Machine { id, error } => write!(f, "machine {}: {}", id, error),
+            NetworkError::UnknownAddress(address) => {
+                write!(f, "no machine with address {}", address)
+            }
+        }
+    }
+}
+
+impl std::error::Error for NetworkError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NetworkError::Machine { error, .. } => error.source(),
+            NetworkError::UnknownAddress(_) => None,
+        }
+    }
+}
+
+/// An error loading an Intcode program or [`Snapshot`](crate::Snapshot).
+#[derive(Debug)]
+pub enum LoadError {
+    /// A value in the program text isn't an integer.
+    InvalidValue {
+        /// The zero-based index of the value, which is also its address.
+        index: usize,
+        text: String,
+        source: ParseIntError,
+    },
+    /// The program is neither a binary program nor UTF-8 text.
+    NotText(Utf8Error),
+    /// The file isn't a snapshot.
+    NotSnapshot,
+    /// A binary program or snapshot was written in a newer version of its
+    /// format.
+    UnsupportedVersion(u32),
+    /// A binary program or snapshot ends before all of its values.
+    Truncated,
+    /// A binary program or snapshot has bytes past the end of its values.
+    TrailingData,
+    Io(std::io::Error),
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::InvalidValue { index, text, .. } => {
+                write!(f, "invalid value {:?} at index {}", text, index)
+            }
+            LoadError::NotText(_) => f.write_str("program is not text"),
+            LoadError::NotSnapshot => f.write_str("not a snapshot"),
+            LoadError::UnsupportedVersion(version) => {
+                write!(f, "unsupported format version {}", version)
+            }
+            LoadError::Truncated => f.write_str("unexpected end of file"),
+            LoadError::TrailingData => f.write_str("unexpected data at end of file"),
+            LoadError::Io(_) => f.write_str("failed to read file"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::InvalidValue { source, .. } => Some(source),
+            LoadError::NotText(error) => Some(error),
+            LoadError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for LoadError {
+    fn from(error: std::io::Error) -> Self {
+        LoadError::Io(error)
+    }
+}
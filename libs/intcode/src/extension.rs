@@ -0,0 +1,280 @@
+use crate::{IntCodeComputer, MemoryBackend, PagedMemory, Status};
+use std::fmt::{self, Debug, Formatter};
+
+/// How a custom opcode uses one of its parameters.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum Role {
+    /// The parameter is read according to its mode, like the inputs of an add.
+    Read,
+    /// The parameter is the address the instruction writes to, like the output
+    /// of an add.
+    Write,
+}
+
+type HandlerResult<B> = anyhow::Result<Option<Status<<B as MemoryBackend>::Word>>>;
+type Handler<B> = dyn Fn(&mut ExtensionCall<'_, B>) -> HandlerResult<B> + Send + Sync;
+
+/// A custom opcode, registered with
+/// [`IntCodeComputer::register_opcode`](crate::IntCodeComputer::register_opcode).
+///
+/// The machine decodes the parameter modes and resolves each parameter by its
+/// role before calling the handler. The handler returns a status like
+/// [`IntCodeComputer::step`], and the machine moves past the instruction
+/// unless the handler jumps elsewhere or returns [`Status::NeedsInput`] or
+/// [`Status::Halted`]. Those leave the machine on the instruction, to run it
+/// again once there's input or, like a built-in halt, to halt again without
+/// counting a step. An error returned by the handler is reported as an
+/// [`ErrorKind::Extension`](crate::ErrorKind::Extension) fault, which makes
+/// trap instructions easy to write.
+pub struct Extension<B: MemoryBackend = PagedMemory> {
+    mnemonic: String,
+    roles: Vec<Role>,
+    handler: Box<Handler<B>>,
+}
+
+impl<B: MemoryBackend> Extension<B> {
+    /// Creates an opcode taking a parameter for each role.
+    pub fn new<F>(mnemonic: &str, roles: &[Role], handler: F) -> Self
+    where
+        F: Fn(&mut ExtensionCall<'_, B>) -> HandlerResult<B> + Send + Sync + 'static,
+    {
+        Extension {
+            mnemonic: mnemonic.to_string(),
+            roles: roles.to_vec(),
+            handler: Box::new(handler),
+        }
+    }
+
+    pub fn mnemonic(&self) -> &str {
+        &self.mnemonic
+    }
+
+    pub fn roles(&self) -> &[Role] {
+        &self.roles
+    }
+
+    pub(crate) fn call(&self, call: &mut ExtensionCall<'_, B>) -> HandlerResult<B> {
+        (self.handler)(call)
+    }
+}
+
+impl<B: MemoryBackend> Debug for Extension<B> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extension")
+            .field("mnemonic", &self.mnemonic)
+            .field("roles", &self.roles)
+            .finish()
+    }
+}
+
+/// A resolved parameter of a custom instruction.
+#[derive(Clone, Debug)]
+pub(crate) enum Argument<W> {
+    Value(W),
+    Address(usize),
+}
+
+/// A custom instruction being executed, passed to its [`Extension`]'s handler.
+///
+/// Handlers can only change the machine by writing to their written parameters
+/// and by jumping, so the instruction pointer is always set by the machine and
+/// every write can be journaled.
+pub struct ExtensionCall<'a, B: MemoryBackend = PagedMemory> {
+    pub(crate) computer: &'a mut IntCodeComputer<B>,
+    pub(crate) arguments: Vec<Argument<B::Word>>,
+    pub(crate) jump: Option<usize>,
+}
+
+impl<'a, B: MemoryBackend> ExtensionCall<'a, B> {
+    /// The value of the parameter with the given zero-based index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the parameter isn't a [`Role::Read`] parameter.
+    pub fn read(&self, parameter: usize) -> B::Word {
+        match &self.arguments[parameter] {
+            Argument::Value(value) => value.clone(),
+            Argument::Address(_) => panic!("parameter {} isn't read", parameter),
+        }
+    }
+
+    /// Writes to the address of the parameter with the given zero-based index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the parameter isn't a [`Role::Write`] parameter.
+    pub fn write(&mut self, parameter: usize, value: B::Word) {
+        match self.arguments[parameter] {
+            Argument::Address(addr) => self.computer.set(addr, value),
+            Argument::Value(_) => panic!("parameter {} isn't written", parameter),
+        }
+    }
+
+    /// Continues at `target` after the instruction instead of the next one.
+    pub fn jump(&mut self, target: usize) {
+        self.jump = Some(target);
+    }
+
+    /// The machine running the instruction. Its instruction pointer is still
+    /// on the instruction.
+    pub fn computer(&self) -> &IntCodeComputer<B> {
+        self.computer
+    }
+}
+
+/// Custom opcodes shared by the tests of modules that handle them.
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::*;
+
+    /// `SUB a, b -> c`, which writes `a - b` to `c`.
+    pub(crate) fn subtract() -> Extension {
+        Extension::new("SUB", &[Role::Read, Role::Read, Role::Write], |call| {
+            let difference = call.read(0) - call.read(1);
+            call.write(2, difference);
+            Ok(None)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fixtures::subtract, *};
+    use crate::{ErrorKind, MemoryValue, Program, Validation};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    fn computer(program: &[MemoryValue]) -> IntCodeComputer {
+        IntCodeComputer::new(&Program::new(program))
+    }
+
+    fn outputs(computer: &mut IntCodeComputer) -> Vec<MemoryValue> {
+        let mut output = Vec::new();
+        while let Status::Output(value) = computer.run().unwrap() {
+            output.push(value);
+        }
+        output
+    }
+
+    #[test]
+    fn custom_opcodes() {
+        // SUB #7, #3 -> [9], then a jump over the halt
+        let program = [1110, 7, 3, 9, 4, 9, 120, 10, 99, 0, 4, 9, 99];
+        let mut computer = computer(&program);
+        computer.register_opcode(10, subtract());
+        computer.register_opcode(
+            20,
+            Extension::new("JMP", &[Role::Read], |call| {
+                let target = call.read(0);
+                call.jump(target as usize);
+                Ok(None)
+            }),
+        );
+        assert_eq!(computer.next_write().unwrap(), Some(9));
+        assert_eq!(outputs(&mut computer), [4, 4]);
+        assert_eq!(computer.steps(), 4);
+
+        // waits for two values of input, then writes how many there are
+        let mut computer = self::computer(&[21, 5, 4, 5, 99, 0]);
+        computer.register_opcode(
+            21,
+            Extension::new("WAIT", &[Role::Write], |call| {
+                let pending = call.computer().pending_input().len();
+                if pending < 2 {
+                    return Ok(Some(Status::NeedsInput));
+                }
+
+                call.write(0, pending as MemoryValue);
+                Ok(None)
+            }),
+        );
+        assert_eq!(computer.run().unwrap(), Status::NeedsInput);
+        assert_eq!((computer.ip(), computer.steps()), (0, 0));
+        computer.provide_input(1);
+        computer.provide_input(2);
+        assert_eq!(outputs(&mut computer), [2]);
+    }
+
+    #[test]
+    fn halts() {
+        // SUB #7, #3 -> [7], then halts before the output
+        let mut computer = computer(&[1110, 7, 3, 7, 50, 4, 7, 0]);
+        computer.register_opcode(10, subtract());
+        computer.register_opcode(
+            50,
+            Extension::new("STOP", &[], |_| Ok(Some(Status::Halted))),
+        );
+        for _ in 0..3 {
+            assert_eq!(computer.run().unwrap(), Status::Halted);
+            assert_eq!((computer.ip(), computer.steps()), (4, 1));
+        }
+        assert_eq!(computer.get(7), 4);
+    }
+
+    #[test]
+    fn overrides() {
+        // counts adds while still adding
+        let adds = Arc::new(AtomicUsize::new(0));
+        let counter = adds.clone();
+        let mut computer = computer(&[1, 0, 0, 0, 1101, 1, 2, 9, 99, 0]);
+        computer.register_opcode(
+            1,
+            Extension::new("ADD", &[Role::Read, Role::Read, Role::Write], move |call| {
+                counter.fetch_add(1, Ordering::Relaxed);
+                let sum = call.read(0) + call.read(1);
+                call.write(2, sum);
+                Ok(None)
+            }),
+        );
+        assert_eq!(computer.run().unwrap(), Status::Halted);
+        assert_eq!((computer.get(0), computer.get(9)), (2, 3));
+        assert_eq!(adds.load(Ordering::Relaxed), 2);
+
+        let extension = computer.unregister_opcode(1).unwrap();
+        assert_eq!(extension.mnemonic(), "ADD");
+        computer.set_ip(0);
+        computer.run().unwrap();
+        assert_eq!(computer.get(0), 4);
+        assert_eq!(adds.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn faults() {
+        let mut computer = computer(&[4, 0, 42, 99]);
+        computer.register_opcode(
+            42,
+            Extension::new("TRAP", &[], |_| anyhow::bail!("trapped")),
+        );
+        assert_eq!(computer.run().unwrap(), Status::Output(4));
+        let error = computer.run().unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::Extension(_)));
+        assert_eq!((error.ip, error.instruction), (2, 42));
+        assert!(error.is_fault());
+
+        // parameters are checked like those of built-in opcodes
+        let mut computer = self::computer(&[30010, 1, 1, 5, 99, 0]);
+        computer.register_opcode(10, subtract());
+        let error = computer.run().unwrap_err();
+        assert!(matches!(
+            error.kind,
+            ErrorKind::UnknownMode {
+                parameter: 2,
+                mode: 3
+            }
+        ));
+
+        let mut computer = self::computer(&[100010, 1, 1, 5, 99, 0]);
+        computer.register_opcode(10, subtract());
+        computer.set_validation(Validation::Strict);
+        let error = computer.run().unwrap_err();
+        assert!(matches!(
+            error.kind,
+            ErrorKind::UnexpectedMode {
+                parameter: 3,
+                mode: 1
+            }
+        ));
+    }
+}
@@ -0,0 +1,515 @@
+use crate::{Instruction, Item, Line, MemoryValue, Mode, Opcode, Operand};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+    fmt::Write,
+    ops::Range,
+};
+
+/// How control passes from one basic block to another.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum EdgeKind {
+    /// Execution continues with the next instruction.
+    Next,
+    Jump,
+    /// A jump made right after pushing the address following it onto the
+    /// stack, which is likely a function call.
+    Call,
+    /// From a call to the address after it, where the called function likely
+    /// returns to.
+    Return,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct Edge {
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+/// How the last instruction of a basic block leaves it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum BlockEnd {
+    /// Every way out of the block is one of its edges.
+    Branch,
+    Halt,
+    /// Jumps to an address read relative to the relative base, which is
+    /// likely a function returning to an address on the stack.
+    Return,
+    /// Jumps to an address read from memory.
+    IndirectJump,
+    /// Runs into a value that isn't an instruction, an instruction that runs
+    /// past the end of memory, or a jump to a negative address.
+    Invalid,
+}
+
+/// A run of instructions that's always executed from start to end.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct BasicBlock {
+    pub start: usize,
+    /// The block's instructions. Blocks that start at an invalid instruction
+    /// have none.
+    pub lines: Vec<Line>,
+    pub edges: Vec<Edge>,
+    pub end: BlockEnd,
+}
+
+impl BasicBlock {
+    /// The addresses the block's instructions occupy.
+    pub fn range(&self) -> Range<usize> {
+        let len: usize = self.lines.iter().map(|line| line.item.len()).sum();
+        self.start..self.start + len
+    }
+}
+
+/// An instruction that writes to an address execution reaches as code.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct SelfModifyingWrite {
+    /// The address of the writing instruction.
+    pub addr: usize,
+    pub target: usize,
+}
+
+/// The control flow graph of a program, found by following every jump with a
+/// constant target from address 0.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct ControlFlowGraph {
+    /// The reachable basic blocks, ordered by address.
+    pub blocks: Vec<BasicBlock>,
+    /// The ranges of memory no reachable instruction occupies.
+    pub data: Vec<Range<usize>>,
+    /// Writes to constant addresses that hold reachable instructions, or that
+    /// execution reaches before they hold valid ones. Writes to addresses
+    /// relative to the relative base aren't known statically.
+    pub self_modifying_writes: Vec<SelfModifyingWrite>,
+}
+
+/// A reachable instruction and where it can go next. Instructions with no end
+/// fall through to the next instruction.
+struct Decoded {
+    line: Line,
+    edges: Vec<Edge>,
+    end: Option<BlockEnd>,
+}
+
+fn decode_at(memory: &[MemoryValue], addr: usize) -> Option<(Instruction, Vec<Operand>)> {
+    let instruction = Instruction::decode(*memory.get(addr)?).ok()?;
+    let parameters = memory.get(addr + 1..addr + instruction.size())?;
+    let operands = parameters
+        .iter()
+        .zip(&instruction.modes)
+        .map(|(&value, &mode)| Operand { mode, value })
+        .collect();
+    Some((instruction, operands))
+}
+
+/// The constant an instruction pushes onto the stack, if it writes one to an
+/// address relative to the relative base.
+fn pushed_constant(instruction: Instruction, operands: &[Operand]) -> Option<MemoryValue> {
+    match operands {
+        [Operand {
+            mode: Mode::Immediate,
+            value: a,
+        }, Operand {
+            mode: Mode::Immediate,
+            value: b,
+        }, Operand {
+            mode: Mode::Relative,
+            ..
+        }] => match instruction.opcode {
+            Opcode::Add => a.checked_add(*b),
+            Opcode::Multiply => a.checked_mul(*b),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Where a jump instruction can go. `pushed` is the constant the previous
+/// instruction pushed onto the stack, if any.
+fn jump_flow(
+    instruction: Instruction,
+    operands: &[Operand],
+    next: usize,
+    pushed: Option<MemoryValue>,
+) -> (Vec<Edge>, Option<BlockEnd>) {
+    let (condition, target) = (operands[0], operands[1]);
+    let taken = match condition.mode {
+        Mode::Immediate => {
+            Some((condition.value != 0) == (instruction.opcode == Opcode::JumpIfTrue))
+        }
+        _ => None,
+    };
+
+    let mut edges = Vec::new();
+    let end = match (taken, target.mode) {
+        // never taken, so it's no different to any other instruction
+        (Some(false), _) => return (edges, None),
+        (_, Mode::Immediate) => match usize::try_from(target.value) {
+            Ok(to) if taken.is_some() && pushed == Some(next as MemoryValue) => {
+                edges.push(Edge {
+                    to,
+                    kind: EdgeKind::Call,
+                });
+                edges.push(Edge {
+                    to: next,
+                    kind: EdgeKind::Return,
+                });
+                BlockEnd::Branch
+            }
+            Ok(to) => {
+                edges.push(Edge {
+                    to,
+                    kind: EdgeKind::Jump,
+                });
+                BlockEnd::Branch
+            }
+            Err(_) if taken.is_some() => BlockEnd::Invalid,
+            Err(_) => BlockEnd::Branch,
+        },
+        (_, Mode::Relative) => BlockEnd::Return,
+        (_, Mode::Position) => BlockEnd::IndirectJump,
+    };
+
+    if taken.is_none() {
+        edges.push(Edge {
+            to: next,
+            kind: EdgeKind::Next,
+        });
+    }
+    (edges, Some(end))
+}
+
+/// Finds the reachable instructions in memory, and the addresses execution
+/// can reach that don't hold valid instructions.
+fn explore(memory: &[MemoryValue]) -> (BTreeMap<usize, Decoded>, BTreeSet<usize>) {
+    let mut decoded: BTreeMap<usize, Decoded> = BTreeMap::new();
+    let mut invalid = BTreeSet::new();
+    let mut queue = vec![0];
+    while let Some(mut addr) = queue.pop() {
+        let mut pushed = None;
+        while !decoded.contains_key(&addr) && !invalid.contains(&addr) {
+            let (instruction, operands) = match decode_at(memory, addr) {
+                Some(decoded) => decoded,
+                None => {
+                    invalid.insert(addr);
+                    break;
+                }
+            };
+
+            let next = addr + instruction.size();
+            let (edges, end) = match instruction.opcode {
+                Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+                    jump_flow(instruction, &operands, next, pushed)
+                }
+                Opcode::Halt => (Vec::new(), Some(BlockEnd::Halt)),
+                _ => (Vec::new(), None),
+            };
+            pushed = pushed_constant(instruction, &operands);
+
+            queue.extend(edges.iter().map(|edge| edge.to));
+            let falls_through = end.is_none();
+            decoded.insert(
+                addr,
+                Decoded {
+                    line: Line {
+                        addr,
+                        item: Item::Instruction {
+                            instruction,
+                            operands,
+                        },
+                    },
+                    edges,
+                    end,
+                },
+            );
+
+            if !falls_through {
+                break;
+            }
+            addr = next;
+        }
+    }
+
+    (decoded, invalid)
+}
+
+impl ControlFlowGraph {
+    /// Builds the control flow graph of the program in `memory`.
+    pub fn new(memory: &[MemoryValue]) -> Self {
+        let (mut decoded, invalid) = explore(memory);
+
+        let mut leaders: BTreeSet<usize> = decoded
+            .values()
+            .flat_map(|decoded| decoded.edges.iter().map(|edge| edge.to))
+            .chain(invalid.iter().copied())
+            .collect();
+        leaders.insert(0);
+
+        let mut blocks = Vec::new();
+        for &start in &leaders {
+            let mut block = BasicBlock {
+                start,
+                lines: Vec::new(),
+                edges: Vec::new(),
+                end: BlockEnd::Invalid,
+            };
+
+            let mut addr = start;
+            while let Some(instruction) = decoded.remove(&addr) {
+                addr += instruction.line.item.len();
+                block.lines.push(instruction.line);
+                if let Some(end) = instruction.end {
+                    block.edges = instruction.edges;
+                    block.end = end;
+                    break;
+                }
+
+                if leaders.contains(&addr) {
+                    block.edges.push(Edge {
+                        to: addr,
+                        kind: EdgeKind::Next,
+                    });
+                    block.end = BlockEnd::Branch;
+                    break;
+                }
+            }
+
+            blocks.push(block);
+        }
+
+        let mut code = vec![false; memory.len()];
+        for block in &blocks {
+            for addr in block.range() {
+                code[addr] = true;
+            }
+        }
+
+        let mut data: Vec<Range<usize>> = Vec::new();
+        for addr in (0..memory.len()).filter(|&addr| !code[addr]) {
+            match data.last_mut() {
+                Some(range) if range.end == addr => range.end += 1,
+                _ => data.push(addr..addr + 1),
+            }
+        }
+
+        let mut self_modifying_writes = Vec::new();
+        for line in blocks.iter().flat_map(|block| &block.lines) {
+            if let Item::Instruction {
+                instruction,
+                operands,
+            } = &line.item
+            {
+                let output = instruction.opcode.output_parameter().map(|i| operands[i]);
+                if let Some(Operand {
+                    mode: Mode::Position,
+                    value,
+                }) = output
+                {
+                    match usize::try_from(value) {
+                        Ok(target)
+                            if code.get(target) == Some(&true) || invalid.contains(&target) =>
+                        {
+                            self_modifying_writes.push(SelfModifyingWrite {
+                                addr: line.addr,
+                                target,
+                            })
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        ControlFlowGraph {
+            blocks,
+            data,
+            self_modifying_writes,
+        }
+    }
+
+    /// The block containing the instruction at `addr`.
+    pub fn block_at(&self, addr: usize) -> Option<&BasicBlock> {
+        self.blocks
+            .iter()
+            .find(|block| block.range().contains(&addr) || block.start == addr)
+    }
+
+    /// Renders the graph in Graphviz DOT format, with a node for each basic
+    /// block and data region.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        // writing to a string can't fail
+        self.write_dot(&mut dot).unwrap();
+        dot
+    }
+
+    fn write_dot(&self, dot: &mut String) -> std::fmt::Result {
+        writeln!(dot, "digraph intcode {{")?;
+        writeln!(dot, "    node [shape=box, fontname=\"monospace\"];")?;
+
+        for block in &self.blocks {
+            let mut label = String::new();
+            for line in &block.lines {
+                write!(label, "{}\\l", line)?;
+            }
+            match block.end {
+                BlockEnd::Branch | BlockEnd::Halt => {}
+                BlockEnd::Return => label.push_str("(return)\\l"),
+                BlockEnd::IndirectJump => label.push_str("(indirect jump)\\l"),
+                BlockEnd::Invalid if block.lines.is_empty() => {
+                    write!(label, "{:04}: (invalid)\\l", block.start)?
+                }
+                BlockEnd::Invalid => label.push_str("(invalid)\\l"),
+            }
+            writeln!(dot, "    b{} [label=\"{}\"];", block.start, label)?;
+
+            for edge in &block.edges {
+                let attributes = match edge.kind {
+                    EdgeKind::Next => "",
+                    EdgeKind::Jump => " [label=\"jump\"]",
+                    EdgeKind::Call => " [label=\"call\", style=bold]",
+                    EdgeKind::Return => " [label=\"return\", style=dashed]",
+                };
+                writeln!(dot, "    b{} -> b{}{};", block.start, edge.to, attributes)?;
+            }
+        }
+
+        for range in &self.data {
+            writeln!(
+                dot,
+                "    d{} [shape=note, label=\"{:04}-{:04}: data\"];",
+                range.start,
+                range.start,
+                range.end - 1
+            )?;
+        }
+
+        for write in &self.self_modifying_writes {
+            let from = self.block_at(write.addr).map(|block| block.start);
+            let to = self.block_at(write.target).map(|block| block.start);
+            if let (Some(from), Some(to)) = (from, to) {
+                writeln!(
+                    dot,
+                    "    b{} -> b{} [label=\"writes {}\", style=dotted, color=red];",
+                    from, to, write.target
+                )?;
+            }
+        }
+
+        writeln!(dot, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble;
+
+    fn graph(source: &str) -> ControlFlowGraph {
+        ControlFlowGraph::new(&assemble(source).unwrap())
+    }
+
+    fn edges(block: &BasicBlock) -> Vec<(usize, EdgeKind)> {
+        block
+            .edges
+            .iter()
+            .map(|edge| (edge.to, edge.kind))
+            .collect()
+    }
+
+    #[test]
+    fn blocks() {
+        let graph = graph(
+            "
+                    IN -> [value]
+            loop:   OUT [value]
+                    ADD [value], #-1 -> [value]
+                    JT [value], #loop
+                    JT #0, #loop
+                    JF #0, #end
+                    DATA 1, 2, 3
+            end:    HLT
+            value:  DATA 0
+            ",
+        );
+
+        let starts: Vec<_> = graph.blocks.iter().map(|block| block.start).collect();
+        assert_eq!(starts, [0, 2, 11, 20]);
+        assert_eq!(graph.blocks[0].lines.len(), 1);
+        assert_eq!(edges(&graph.blocks[0]), [(2, EdgeKind::Next)]);
+        assert_eq!(
+            edges(&graph.blocks[1]),
+            [(2, EdgeKind::Jump), (11, EdgeKind::Next)]
+        );
+
+        // a jump that's never taken doesn't end the block
+        assert_eq!(graph.blocks[2].range(), 11..17);
+        assert_eq!(edges(&graph.blocks[2]), [(20, EdgeKind::Jump)]);
+        assert_eq!(graph.blocks[3].end, BlockEnd::Halt);
+        assert_eq!(graph.data, [17..20, 21..22]);
+        assert!(graph.self_modifying_writes.is_empty());
+    }
+
+    #[test]
+    fn calls() {
+        let graph = graph(
+            "
+                    ARB #stack
+                    ADD #0, #after -> [rb+0]
+                    JT #1, #double
+            after:  OUT [rb+1]
+                    HLT
+            double: MUL [rb+1], #2 -> [rb+1]
+                    JF #0, [rb+0]
+            stack:  DATA 0, 0
+            ",
+        );
+
+        assert_eq!(
+            edges(&graph.blocks[0]),
+            [(12, EdgeKind::Call), (9, EdgeKind::Return)]
+        );
+        let double = graph.block_at(12).unwrap();
+        assert_eq!(double.end, BlockEnd::Return);
+        assert!(double.edges.is_empty());
+        assert_eq!(graph.block_at(9).unwrap().end, BlockEnd::Halt);
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph intcode {\n"));
+        assert!(dot.contains("    b0 -> b12 [label=\"call\", style=bold];\n"));
+        assert!(dot.contains("    b0 -> b9 [label=\"return\", style=dashed];\n"));
+        assert!(dot.contains("0012: MUL [rb+1], #2 -> [rb+1]\\l0016: JF #0, [rb+0]\\l(return)\\l"));
+        assert!(dot.contains("    d19 [shape=note, label=\"0019-0020: data\"];\n"));
+    }
+
+    #[test]
+    fn unusual_flow() {
+        let graph = graph(
+            "
+                    ADD #99, #0 -> [patch]
+                    JT [flag], [target]
+            patch:  DATA 0
+            flag:   DATA 0
+            target: DATA -1
+            ",
+        );
+
+        assert_eq!(graph.blocks[0].end, BlockEnd::IndirectJump);
+        assert_eq!(edges(&graph.blocks[0]), [(7, EdgeKind::Next)]);
+        assert_eq!(
+            graph.self_modifying_writes,
+            [SelfModifyingWrite { addr: 0, target: 7 }]
+        );
+        // the patched address isn't an instruction until the program runs
+        let patch = graph.block_at(7).unwrap();
+        assert_eq!((patch.lines.len(), patch.end), (0, BlockEnd::Invalid));
+        assert!(graph.to_dot().contains("0007: (invalid)"));
+
+        // jumps to negative addresses and running off the end are invalid
+        let graph = ControlFlowGraph::new(&[1105, 1, -1]);
+        assert_eq!(graph.blocks[0].end, BlockEnd::Invalid);
+        let graph = ControlFlowGraph::new(&[1101, 1, 1, 3]);
+        assert_eq!(edges(&graph.blocks[0]), [(4, EdgeKind::Next)]);
+        assert_eq!(graph.blocks[1].start, 4);
+    }
+}
@@ -0,0 +1,256 @@
+use crate::{ErrorKind, MemoryValue};
+use std::fmt::{self, Display, Formatter};
+
+/// The operation performed by an instruction.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum Opcode {
+    Add,
+    Multiply,
+    Input,
+    Output,
+    JumpIfTrue,
+    JumpIfFalse,
+    LessThan,
+    Equals,
+    AdjustRelativeBase,
+    Halt,
+}
+
+impl Opcode {
+    pub const ALL: [Opcode; 10] = [
+        Opcode::Add,
+        Opcode::Multiply,
+        Opcode::Input,
+        Opcode::Output,
+        Opcode::JumpIfTrue,
+        Opcode::JumpIfFalse,
+        Opcode::LessThan,
+        Opcode::Equals,
+        Opcode::AdjustRelativeBase,
+        Opcode::Halt,
+    ];
+
+    pub fn from_value(value: MemoryValue) -> Option<Self> {
+        let opcode = match value {
+            1 => Opcode::Add,
+            2 => Opcode::Multiply,
+            3 => Opcode::Input,
+            4 => Opcode::Output,
+            5 => Opcode::JumpIfTrue,
+            6 => Opcode::JumpIfFalse,
+            7 => Opcode::LessThan,
+            8 => Opcode::Equals,
+            9 => Opcode::AdjustRelativeBase,
+            99 => Opcode::Halt,
+            _ => return None,
+        };
+
+        Some(opcode)
+    }
+
+    pub fn value(self) -> MemoryValue {
+        match self {
+            Opcode::Add => 1,
+            Opcode::Multiply => 2,
+            Opcode::Input => 3,
+            Opcode::Output => 4,
+            Opcode::JumpIfTrue => 5,
+            Opcode::JumpIfFalse => 6,
+            Opcode::LessThan => 7,
+            Opcode::Equals => 8,
+            Opcode::AdjustRelativeBase => 9,
+            Opcode::Halt => 99,
+        }
+    }
+
+    /// The number of parameters the instruction takes.
+    pub fn arity(self) -> usize {
+        match self {
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equals => 3,
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => 2,
+            Opcode::Input | Opcode::Output | Opcode::AdjustRelativeBase => 1,
+            Opcode::Halt => 0,
+        }
+    }
+
+    /// The index of the parameter the instruction writes to, if any.
+    pub fn output_parameter(self) -> Option<usize> {
+        match self {
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equals => Some(2),
+            Opcode::Input => Some(0),
+            _ => None,
+        }
+    }
+
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            Opcode::Add => "ADD",
+            Opcode::Multiply => "MUL",
+            Opcode::Input => "IN",
+            Opcode::Output => "OUT",
+            Opcode::JumpIfTrue => "JT",
+            Opcode::JumpIfFalse => "JF",
+            Opcode::LessThan => "LT",
+            Opcode::Equals => "EQ",
+            Opcode::AdjustRelativeBase => "ARB",
+            Opcode::Halt => "HLT",
+        }
+    }
+
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Self> {
+        Opcode::ALL
+            .iter()
+            .copied()
+            .find(|opcode| opcode.mnemonic() == mnemonic)
+    }
+}
+
+impl Display for Opcode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.mnemonic())
+    }
+}
+
+/// How an instruction's parameter is interpreted.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum Mode {
+    /// The parameter is the address of the value.
+    Position,
+    /// The parameter is the value itself.
+    Immediate,
+    /// The parameter is the address of the value, offset by the relative base.
+    Relative,
+}
+
+impl Mode {
+    pub fn from_value(value: MemoryValue) -> Option<Self> {
+        match value {
+            0 => Some(Mode::Position),
+            1 => Some(Mode::Immediate),
+            2 => Some(Mode::Relative),
+            _ => None,
+        }
+    }
+
+    pub fn value(self) -> MemoryValue {
+        match self {
+            Mode::Position => 0,
+            Mode::Immediate => 1,
+            Mode::Relative => 2,
+        }
+    }
+}
+
+/// A decoded instruction: its opcode and the mode of each of its parameters.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub modes: [Mode; 3],
+}
+
+impl Instruction {
+    pub fn new(opcode: Opcode, modes: [Mode; 3]) -> Self {
+        Instruction { opcode, modes }
+    }
+
+    /// Decodes an instruction from its value in memory. The modes of
+    /// parameters the opcode doesn't take are ignored.
+    pub fn decode(value: MemoryValue) -> Result<Self, ErrorKind> {
+        let opcode = match Opcode::from_value(value % 100) {
+            Some(opcode) => opcode,
+            None => return Err(ErrorKind::UnknownOpcode(value % 100)),
+        };
+
+        let mut modes = [Mode::Position; 3];
+        let mut digits = value / 100;
+        for (parameter, mode) in modes.iter_mut().take(opcode.arity()).enumerate() {
+            *mode = match Mode::from_value(digits % 10) {
+                Some(mode) => mode,
+                None => {
+                    return Err(ErrorKind::UnknownMode {
+                        parameter,
+                        mode: digits % 10,
+                    })
+                }
+            };
+            digits /= 10;
+        }
+
+        Ok(Instruction { opcode, modes })
+    }
+
+    pub fn encode(self) -> MemoryValue {
+        self.modes
+            .iter()
+            .rev()
+            .fold(0, |digits, mode| digits * 10 + mode.value())
+            * 100
+            + self.opcode.value()
+    }
+
+    /// The number of memory cells the instruction occupies, including its
+    /// parameters.
+    pub fn size(self) -> usize {
+        self.opcode.arity() + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode() {
+        assert_eq!(
+            Instruction::decode(1002).unwrap(),
+            Instruction::new(
+                Opcode::Multiply,
+                [Mode::Position, Mode::Immediate, Mode::Position]
+            )
+        );
+        assert_eq!(
+            Instruction::decode(21107).unwrap(),
+            Instruction::new(
+                Opcode::LessThan,
+                [Mode::Immediate, Mode::Immediate, Mode::Relative]
+            )
+        );
+        assert_eq!(
+            Instruction::decode(204).unwrap(),
+            Instruction::new(
+                Opcode::Output,
+                [Mode::Relative, Mode::Position, Mode::Position]
+            )
+        );
+
+        // modes of unused parameters are ignored
+        assert_eq!(Instruction::decode(99999).unwrap().opcode, Opcode::Halt);
+        assert!(matches!(
+            Instruction::decode(42),
+            Err(ErrorKind::UnknownOpcode(42))
+        ));
+        assert!(matches!(
+            Instruction::decode(-1),
+            Err(ErrorKind::UnknownOpcode(-1))
+        ));
+        assert!(matches!(
+            Instruction::decode(3001),
+            Err(ErrorKind::UnknownMode {
+                parameter: 1,
+                mode: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn encode() {
+        for &opcode in &Opcode::ALL {
+            assert_eq!(Opcode::from_value(opcode.value()), Some(opcode));
+            assert_eq!(Opcode::from_mnemonic(opcode.mnemonic()), Some(opcode));
+        }
+
+        for &value in &[1, 2, 1002, 21107, 204, 1105, 99, 109, 203] {
+            assert_eq!(Instruction::decode(value).unwrap().encode(), value);
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use crate::{IntCodeComputer, IntcodeError, MemoryValue, Status};
+
+/// A value overwritten by an instruction.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct Overwrite {
+    pub addr: usize,
+    pub old: MemoryValue,
+    pub new: MemoryValue,
+}
+
+/// The state of a machine before it executed an instruction, and what the
+/// instruction changed.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct JournalEntry {
+    /// The number of instructions the machine had executed before this one.
+    pub step: u64,
+    pub ip: usize,
+    pub relative_base: i64,
+    /// The values at each address the instruction could write to. Built-in
+    /// instructions write to at most one address, while custom opcodes can
+    /// write to each of their written parameters.
+    pub writes: Vec<Overwrite>,
+    /// The input value the instruction read, if it read one.
+    pub input: Option<MemoryValue>,
+}
+
+/// A record of the instructions executed by an [`IntCodeComputer`], which can
+/// be undone to step the machine backwards.
+///
+/// Only instructions executed through [`Journal::step`] are recorded, so
+/// changes made to the machine in other ways aren't undone. Output can't be
+/// taken back, so stepping back over an output instruction only moves the
+/// machine back to before it.
+#[derive(Clone, Debug, Default)]
+pub struct Journal {
+    entries: Vec<JournalEntry>,
+}
+
+impl JournalEntry {
+    /// What the instruction wrote to `addr`, if it wrote to it.
+    pub fn write_to(&self, addr: usize) -> Option<&Overwrite> {
+        self.writes.iter().rev().find(|write| write.addr == addr)
+    }
+}
+
+impl Journal {
+    pub fn new() -> Self {
+        Journal::default()
+    }
+
+    /// Executes a single instruction like [`IntCodeComputer::step`], recording
+    /// it if it was executed.
+    pub fn step(&mut self, computer: &mut IntCodeComputer) -> Result<Option<Status>, IntcodeError> {
+        let step = computer.steps();
+        let ip = computer.ip();
+        let relative_base = computer.relative_base();
+        let targets = computer.next_writes()?;
+        let old: Vec<_> = targets.iter().map(|&addr| computer.get(addr)).collect();
+        let pending = computer.pending_input().len();
+        let input = computer.pending_input().front().copied();
+
+        let status = computer.step()?;
+        if status == Some(Status::NeedsInput) {
+            return Ok(status);
+        }
+
+        self.entries.push(JournalEntry {
+            step,
+            ip,
+            relative_base,
+            writes: targets
+                .into_iter()
+                .zip(old)
+                .map(|(addr, old)| Overwrite {
+                    addr,
+                    old,
+                    new: computer.get(addr),
+                })
+                .collect(),
+            input: input.filter(|_| computer.pending_input().len() < pending),
+        });
+        Ok(status)
+    }
+
+    /// Runs the program like [`IntCodeComputer::run`], recording each
+    /// instruction executed.
+    pub fn run(&mut self, computer: &mut IntCodeComputer) -> Result<Status, IntcodeError> {
+        loop {
+            if let Some(status) = self.step(computer)? {
+                return Ok(status);
+            }
+        }
+    }
+
+    /// Undoes the last recorded instruction, returning the machine to the
+    /// state it was in before executing it. The machine must be the one the
+    /// instruction was recorded on. Returns the undone entry, or `None` if
+    /// there's nothing left to undo.
+    pub fn step_back(&mut self, computer: &mut IntCodeComputer) -> Option<JournalEntry> {
+        let entry = self.entries.pop()?;
+        computer.undo(&entry);
+        Some(entry)
+    }
+
+    /// The recorded instructions, oldest first.
+    pub fn entries(&self) -> &[JournalEntry] {
+        &self.entries
+    }
+
+    /// The most recent recorded instruction that wrote to `addr`.
+    pub fn last_write(&self, addr: usize) -> Option<&JournalEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.write_to(addr).is_some())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Forgets every recorded instruction, so they can no longer be undone.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assemble, Extension, Program, Role};
+
+    /// Sums its inputs until it reads a 0, writing the running total each time
+    /// and keeping its inputs on a stack.
+    const SUM: &str = "
+                ARB #stack
+        loop:   IN -> [rb]
+                ADD [total], [rb] -> [total]
+                OUT [total]
+                ARB #1
+                JT [rb-1], #loop
+                HLT
+        total:  DATA 0
+        stack:  DATA 0
+    ";
+
+    fn computer() -> IntCodeComputer {
+        let program = Program::from(assemble(SUM).unwrap());
+        let mut computer = IntCodeComputer::new(&program);
+        for &value in &[3, 4, 0] {
+            computer.provide_input(value);
+        }
+        computer
+    }
+
+    fn states(computer: &IntCodeComputer) -> (usize, i64, u64, Vec<MemoryValue>) {
+        let memory = (0..30).map(|addr| computer.get(addr)).collect();
+        (
+            computer.ip(),
+            computer.relative_base(),
+            computer.steps(),
+            memory,
+        )
+    }
+
+    #[test]
+    fn step_back() {
+        let mut computer = computer();
+        let mut journal = Journal::new();
+        let mut history = vec![(states(&computer), computer.pending_input().clone())];
+        while journal.step(&mut computer).unwrap() != Some(Status::Halted) {
+            history.push((states(&computer), computer.pending_input().clone()));
+        }
+        assert_eq!(journal.len(), 17);
+
+        // the halt itself is undone first
+        assert!(journal.step_back(&mut computer).is_some());
+        while let Some((state, input)) = history.pop() {
+            assert_eq!(states(&computer), state);
+            assert_eq!(computer.pending_input(), &input);
+            if history.is_empty() {
+                assert!(journal.step_back(&mut computer).is_none());
+            } else {
+                journal.step_back(&mut computer).unwrap();
+            }
+        }
+
+        // running forward again gives the same results
+        let mut output = Vec::new();
+        while let Status::Output(value) = journal.run(&mut computer).unwrap() {
+            output.push(value);
+        }
+        assert_eq!(output, [3, 7, 7]);
+    }
+
+    #[test]
+    fn last_write() {
+        let mut computer = computer();
+        let mut journal = Journal::new();
+        while journal.run(&mut computer).unwrap() != Status::Halted {}
+
+        assert_eq!(computer.get(16), 7);
+        let entry = journal.last_write(16).unwrap();
+        assert_eq!((entry.step, entry.ip), (12, 4));
+        assert_eq!(
+            entry.writes,
+            [Overwrite {
+                addr: 16,
+                old: 7,
+                new: 7
+            }]
+        );
+
+        assert_eq!(journal.last_write(18).unwrap().input, Some(4));
+        assert_eq!(journal.last_write(19).unwrap().input, Some(0));
+        assert!(journal.last_write(20).is_none());
+    }
+
+    #[test]
+    fn custom_opcodes() {
+        // SWAP [a], [b], then writes both
+        let program = Program::new(&[10, 7, 8, 4, 7, 99, 0, 1, 2]);
+        let mut computer = IntCodeComputer::new(&program);
+        computer.register_opcode(
+            10,
+            Extension::new("SWAP", &[Role::Write, Role::Write], |call| {
+                let computer = call.computer();
+                let (a, b) = (computer.get(7), computer.get(8));
+                call.write(0, b);
+                call.write(1, a);
+                Ok(None)
+            }),
+        );
+
+        let mut journal = Journal::new();
+        assert_eq!(journal.step(&mut computer).unwrap(), None);
+        assert_eq!((computer.get(7), computer.get(8)), (2, 1));
+        assert_eq!(journal.last_write(8).unwrap().write_to(8).unwrap().old, 2);
+        assert_eq!(journal.entries()[0].writes.len(), 2);
+
+        journal.step_back(&mut computer).unwrap();
+        assert_eq!((computer.get(7), computer.get(8)), (1, 2));
+        assert_eq!((computer.ip(), computer.steps()), (0, 0));
+    }
+}
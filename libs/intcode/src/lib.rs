@@ -0,0 +1,50 @@
+mod ascii;
+mod assembler;
+mod computer;
+mod conformance;
+mod debugger;
+mod decompiler;
+mod disassembler;
+mod error;
+mod extension;
+mod flow;
+mod instruction;
+mod journal;
+mod limits;
+mod loader;
+mod memory;
+mod network;
+mod profile;
+mod program;
+mod search;
+mod snapshot;
+mod symbolic;
+mod trace;
+mod word;
+
+pub use ascii::{decode_ascii, AsciiComputer, AsciiOutput, AsciiStatus};
+pub use assembler::assemble;
+pub use computer::{IntCodeComputer, MemoryValue, Status, Validation};
+pub use conformance::{cases, check_conformance, Case, Mismatch, Outcome};
+pub use debugger::{Debugger, Event};
+pub use decompiler::decompile;
+pub use disassembler::{disassemble, Item, Line, Operand};
+pub use error::{ErrorKind, IntcodeError, LoadError, NetworkError, TraceError};
+pub use extension::{Extension, ExtensionCall, Role};
+pub use flow::{BasicBlock, BlockEnd, ControlFlowGraph, Edge, EdgeKind, SelfModifyingWrite};
+pub use instruction::{Instruction, Mode, Opcode};
+pub use journal::{Journal, JournalEntry, Overwrite};
+pub use limits::Limits;
+pub use loader::{load_program, load_program_file, parse_program, write_binary_program};
+pub use memory::{Memory, MemoryBackend, PagedMemory, SparseMemory};
+pub use network::{Endpoint, Network, NetworkStatus, Packet, PacketNetwork};
+pub use profile::{BlockStats, Profile};
+pub use program::Program;
+pub use search::search;
+pub use snapshot::Snapshot;
+pub use symbolic::{Expr, SymbolicComputer};
+pub use trace::{
+    first_divergence, read_binary_trace, Access, BinaryTraceWriter, JsonLinesWriter, TraceEntry,
+    TraceSink, Tracer,
+};
+pub use word::Word;
@@ -0,0 +1,59 @@
+use std::time::{Duration, Instant};
+
+/// Limits on the resources an [`IntCodeComputer`](crate::IntCodeComputer) may
+/// use while running. Exceeding a limit is a fault, and the instruction that
+/// would have exceeded it isn't executed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Limits {
+    /// The most instructions the machine may execute in total.
+    pub max_steps: Option<u64>,
+    /// When the machine must stop running. The deadline is checked whenever
+    /// the machine starts running or executes a single step or a custom
+    /// opcode, but otherwise only every 256 instructions, so a machine may run
+    /// a little past it.
+    pub deadline: Option<Instant>,
+    /// The lowest address the program may not access, read, write or jump to.
+    pub max_address: Option<usize>,
+    /// How many cells may be allocated past the base memory. Writes that would
+    /// allocate more fault, including writes that would allocate a whole page
+    /// of cells in backends that allocate a page at a time.
+    pub max_allocated: Option<usize>,
+}
+
+impl Limits {
+    /// No limits at all.
+    pub fn none() -> Self {
+        Limits::default()
+    }
+
+    pub fn with_max_steps(self, max_steps: u64) -> Self {
+        Limits {
+            max_steps: Some(max_steps),
+            ..self
+        }
+    }
+
+    /// Sets the deadline to `timeout` from now. The machine can run up to 255
+    /// built-in instructions, or one custom opcode, past the deadline before
+    /// noticing it.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Limits {
+            deadline: Some(Instant::now() + timeout),
+            ..self
+        }
+    }
+
+    pub fn with_max_address(self, max_address: usize) -> Self {
+        Limits {
+            max_address: Some(max_address),
+            ..self
+        }
+    }
+
+    pub fn with_max_allocated(self, max_allocated: usize) -> Self {
+        Limits {
+            max_allocated: Some(max_allocated),
+            ..self
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use crate::{LoadError, MemoryValue, Program};
+use std::{convert::TryInto, io::Write, path::Path, str::FromStr};
+
+/// The first bytes of a binary program. Text programs can't start with a NUL.
+const MAGIC: &[u8; 8] = b"\0INTCODE";
+const VERSION: u32 = 1;
+
+/// Parses a program written as comma separated values, such as a puzzle
+/// input. Whitespace around values, including newlines, is ignored.
+pub fn parse_program(text: &str) -> Result<Vec<MemoryValue>, LoadError> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    text.split(',')
+        .enumerate()
+        .map(|(index, s)| {
+            s.trim().parse().map_err(|source| LoadError::InvalidValue {
+                index,
+                text: s.trim().to_string(),
+                source,
+            })
+        })
+        .collect()
+}
+
+/// Writes a program in the binary format read by [`load_program`]: the magic
+/// bytes `\0INTCODE`, then the format version, the number of values and each
+/// value, all little endian. The version is a `u32` and the rest are 64 bits.
+pub fn write_binary_program<W: Write>(
+    values: &[MemoryValue],
+    mut writer: W,
+) -> std::io::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&(values.len() as u64).to_le_bytes())?;
+    for value in values {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+fn read_binary_program(bytes: &[u8]) -> Result<Vec<MemoryValue>, LoadError> {
+    fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8], LoadError> {
+        if bytes.len() < len {
+            return Err(LoadError::Truncated);
+        }
+        let (taken, rest) = bytes.split_at(len);
+        *bytes = rest;
+        Ok(taken)
+    }
+
+    let mut bytes = &bytes[MAGIC.len()..];
+    let version = u32::from_le_bytes(take(&mut bytes, 4)?.try_into().unwrap());
+    if version != VERSION {
+        return Err(LoadError::UnsupportedVersion(version));
+    }
+
+    let len = u64::from_le_bytes(take(&mut bytes, 8)?.try_into().unwrap());
+    let values = match len.checked_mul(8) {
+        Some(size) if size <= bytes.len() as u64 => take(&mut bytes, size as usize)?,
+        _ => return Err(LoadError::Truncated),
+    };
+    if !bytes.is_empty() {
+        return Err(LoadError::TrailingData);
+    }
+
+    Ok(values
+        .chunks_exact(8)
+        .map(|value| MemoryValue::from_le_bytes(value.try_into().unwrap()))
+        .collect())
+}
+
+/// Loads a program written either as text or in the binary format written by
+/// [`write_binary_program`].
+pub fn load_program(bytes: &[u8]) -> Result<Vec<MemoryValue>, LoadError> {
+    if bytes.starts_with(MAGIC) {
+        read_binary_program(bytes)
+    } else {
+        parse_program(std::str::from_utf8(bytes).map_err(LoadError::NotText)?)
+    }
+}
+
+/// Loads a program from a file with [`load_program`].
+pub fn load_program_file<P: AsRef<Path>>(path: P) -> Result<Vec<MemoryValue>, LoadError> {
+    load_program(&std::fs::read(path)?)
+}
+
+impl FromStr for Program {
+    type Err = LoadError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Program::from(parse_program(s)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text() {
+        assert_eq!(parse_program("1,0,0,0,99\n").unwrap(), [1, 0, 0, 0, 99]);
+        assert_eq!(
+            parse_program("  1, -2 ,\n3,\r\n4 \n\n").unwrap(),
+            [1, -2, 3, 4]
+        );
+        assert!(parse_program("\n").unwrap().is_empty());
+
+        match parse_program("1,2,x3,4").unwrap_err() {
+            LoadError::InvalidValue { index, text, .. } => assert_eq!((index, &*text), (2, "x3")),
+            error => panic!("unexpected error: {}", error),
+        }
+        match parse_program("1,,2").unwrap_err() {
+            LoadError::InvalidValue { index, text, .. } => assert_eq!((index, &*text), (1, "")),
+            error => panic!("unexpected error: {}", error),
+        }
+        let error = parse_program("1,2,99999999999999999999").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid value \"99999999999999999999\" at index 2"
+        );
+
+        let program: Program = "1101,2,3,0,99\n".parse().unwrap();
+        assert_eq!(program.len(), 5);
+    }
+
+    #[test]
+    fn binary() {
+        let values = [1, -2, MemoryValue::MAX, MemoryValue::MIN, 99];
+        let mut bytes = Vec::new();
+        write_binary_program(&values, &mut bytes).unwrap();
+        assert_eq!(bytes.len(), 8 + 4 + 8 + 8 * values.len());
+        assert_eq!(load_program(&bytes).unwrap(), values);
+        assert_eq!(load_program(b"3,0,99").unwrap(), [3, 0, 99]);
+
+        assert!(matches!(
+            load_program(&bytes[..bytes.len() - 1]),
+            Err(LoadError::Truncated)
+        ));
+        assert!(matches!(
+            load_program(&bytes[..10]),
+            Err(LoadError::Truncated)
+        ));
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(matches!(
+            load_program(&trailing),
+            Err(LoadError::TrailingData)
+        ));
+
+        let mut newer = bytes.clone();
+        newer[8] = 2;
+        assert!(matches!(
+            load_program(&newer),
+            Err(LoadError::UnsupportedVersion(2))
+        ));
+
+        // a huge length must not overflow or allocate
+        let mut huge = bytes[..12].to_vec();
+        huge.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(load_program(&huge), Err(LoadError::Truncated)));
+
+        assert!(matches!(
+            load_program(b"1,\xff"),
+            Err(LoadError::NotText(_))
+        ));
+    }
+}
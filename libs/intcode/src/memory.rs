@@ -0,0 +1,346 @@
+use crate::{MemoryValue, Word};
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+const PAGE_SIZE: usize = 1024;
+
+type Page<W> = [W; PAGE_SIZE];
+
+fn empty_page<W: Word>() -> Page<W> {
+    std::array::from_fn(|_| W::default())
+}
+
+/// Storage for the addresses past the program image in an Intcode machine's
+/// [`Memory`]. Unwritten addresses read as 0.
+pub trait MemoryBackend: Clone + PartialEq + Debug + Default {
+    /// The type of value stored in memory.
+    type Word: Word;
+
+    fn get(&self, addr: usize) -> Self::Word;
+    fn get_mut(&mut self, addr: usize) -> &mut Self::Word;
+
+    /// The number of cells allocated so far.
+    fn allocated(&self) -> usize;
+
+    /// Whether writing to `addr` can be done without allocating.
+    fn is_allocated(&self, addr: usize) -> bool;
+
+    /// How many cells writing to `addr` allocates if it isn't allocated yet.
+    fn allocation_size(&self, _addr: usize) -> usize {
+        1
+    }
+
+    /// The allocated addresses holding non-zero values, in no particular
+    /// order.
+    fn values(&self) -> Box<dyn Iterator<Item = (usize, Self::Word)> + '_>;
+}
+
+/// Stores each written address separately in a hash map. Compact for programs
+/// that write to a few scattered addresses, but slow to access.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SparseMemory<W = MemoryValue> {
+    values: HashMap<usize, W>,
+}
+
+impl<W> Default for SparseMemory<W> {
+    fn default() -> Self {
+        SparseMemory {
+            values: HashMap::new(),
+        }
+    }
+}
+
+impl<W: Word> MemoryBackend for SparseMemory<W> {
+    type Word = W;
+
+    fn get(&self, addr: usize) -> W {
+        self.values.get(&addr).cloned().unwrap_or_default()
+    }
+
+    fn get_mut(&mut self, addr: usize) -> &mut W {
+        self.values.entry(addr).or_default()
+    }
+
+    fn allocated(&self) -> usize {
+        self.values.len()
+    }
+
+    fn is_allocated(&self, addr: usize) -> bool {
+        self.values.contains_key(&addr)
+    }
+
+    fn values(&self) -> Box<dyn Iterator<Item = (usize, W)> + '_> {
+        Box::new(
+            self.values
+                .iter()
+                .filter(|(_, value)| !value.is_zero())
+                .map(|(&addr, value)| (addr, value.clone())),
+        )
+    }
+}
+
+/// Allocates memory a page at a time, so programs that use a stack or a grid
+/// past the end of their image access it at array speed. Pages are shared
+/// between clones until they're written to.
+///
+/// Addresses past the first few million are stored sparsely instead, so
+/// writing to a huge address doesn't allocate pages for everything before it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PagedMemory<W = MemoryValue> {
+    pages: Vec<Option<Arc<Page<W>>>>,
+    far: SparseMemory<W>,
+}
+
+impl<W> PagedMemory<W> {
+    const MAX_PAGES: usize = 4096;
+}
+
+impl<W> Default for PagedMemory<W> {
+    fn default() -> Self {
+        PagedMemory {
+            pages: Vec::new(),
+            far: SparseMemory::default(),
+        }
+    }
+}
+
+impl<W: Word> MemoryBackend for PagedMemory<W> {
+    type Word = W;
+
+    fn get(&self, addr: usize) -> W {
+        match self.pages.get(addr / PAGE_SIZE) {
+            Some(Some(page)) => page[addr % PAGE_SIZE].clone(),
+            Some(None) => W::default(),
+            None if addr / PAGE_SIZE < Self::MAX_PAGES => W::default(),
+            None => self.far.get(addr),
+        }
+    }
+
+    fn get_mut(&mut self, addr: usize) -> &mut W {
+        let index = addr / PAGE_SIZE;
+        if index >= Self::MAX_PAGES {
+            return self.far.get_mut(addr);
+        }
+
+        if index >= self.pages.len() {
+            self.pages.resize(index + 1, None);
+        }
+
+        let page = self.pages[index].get_or_insert_with(|| Arc::new(empty_page()));
+        &mut Arc::make_mut(page)[addr % PAGE_SIZE]
+    }
+
+    fn allocated(&self) -> usize {
+        self.pages.iter().flatten().count() * PAGE_SIZE + self.far.allocated()
+    }
+
+    fn is_allocated(&self, addr: usize) -> bool {
+        match self.pages.get(addr / PAGE_SIZE) {
+            Some(page) => page.is_some(),
+            None => self.far.is_allocated(addr),
+        }
+    }
+
+    fn allocation_size(&self, addr: usize) -> usize {
+        if addr / PAGE_SIZE < Self::MAX_PAGES {
+            PAGE_SIZE
+        } else {
+            1
+        }
+    }
+
+    fn values(&self) -> Box<dyn Iterator<Item = (usize, W)> + '_> {
+        let paged = self
+            .pages
+            .iter()
+            .enumerate()
+            .filter_map(|(index, page)| page.as_ref().map(|page| (index * PAGE_SIZE, page)))
+            .flat_map(|(start, page)| {
+                page.iter()
+                    .enumerate()
+                    .filter(|(_, value)| !value.is_zero())
+                    .map(move |(offset, value)| (start + offset, value.clone()))
+            });
+        Box::new(paged.chain(self.far.values()))
+    }
+}
+
+/// The memory of an Intcode machine.
+///
+/// The base memory holding the program image is split into copy-on-write
+/// pages, so cloning memory is cheap and clones only copy the pages they write
+/// to. Addresses past the base memory are stored in additional memory, using
+/// the given backend. Unwritten addresses read as 0.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Memory<B: MemoryBackend = PagedMemory> {
+    base_len: usize,
+    pages: Vec<Arc<Page<B::Word>>>,
+    additional: B,
+}
+
+impl<W: Word> Memory<PagedMemory<W>> {
+    pub fn new(base: &[W]) -> Self {
+        let pages = base
+            .chunks(PAGE_SIZE)
+            .map(|chunk| {
+                let mut page = empty_page();
+                page[..chunk.len()].clone_from_slice(chunk);
+                Arc::new(page)
+            })
+            .collect();
+
+        Memory {
+            base_len: base.len(),
+            pages,
+            additional: PagedMemory::default(),
+        }
+    }
+}
+
+impl<B: MemoryBackend> Memory<B> {
+    /// Creates memory sharing this memory's base memory, with empty additional
+    /// memory stored in another backend.
+    pub fn with_backend<C: MemoryBackend<Word = B::Word>>(&self) -> Memory<C> {
+        Memory {
+            base_len: self.base_len,
+            pages: self.pages.clone(),
+            additional: C::default(),
+        }
+    }
+
+    pub fn get(&self, addr: usize) -> B::Word {
+        if addr < self.base_len {
+            self.pages[addr / PAGE_SIZE][addr % PAGE_SIZE].clone()
+        } else {
+            self.additional.get(addr)
+        }
+    }
+
+    pub fn get_mut(&mut self, addr: usize) -> &mut B::Word {
+        if addr < self.base_len {
+            let page = Arc::make_mut(&mut self.pages[addr / PAGE_SIZE]);
+            &mut page[addr % PAGE_SIZE]
+        } else {
+            self.additional.get_mut(addr)
+        }
+    }
+
+    pub fn set(&mut self, addr: usize, value: B::Word) {
+        *self.get_mut(addr) = value;
+    }
+
+    /// The number of addresses in the base memory.
+    pub fn base_len(&self) -> usize {
+        self.base_len
+    }
+
+    /// Iterates over the values in the base memory.
+    pub fn base(&self) -> impl Iterator<Item = B::Word> + '_ {
+        self.pages
+            .iter()
+            .flat_map(|page| page.iter().cloned())
+            .take(self.base_len)
+    }
+
+    /// The number of cells allocated for addresses past the base memory.
+    pub fn allocated(&self) -> usize {
+        self.additional.allocated()
+    }
+
+    /// Whether writing to `addr` can be done without allocating.
+    pub fn is_allocated(&self, addr: usize) -> bool {
+        addr < self.base_len || self.additional.is_allocated(addr)
+    }
+
+    /// How many cells writing to `addr` allocates if it isn't allocated yet.
+    pub fn allocation_size(&self, addr: usize) -> usize {
+        if addr < self.base_len {
+            0
+        } else {
+            self.additional.allocation_size(addr)
+        }
+    }
+
+    /// The memory past the base memory.
+    pub fn additional(&self) -> &B {
+        &self.additional
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reads_and_writes<B: MemoryBackend<Word = MemoryValue>>() {
+        let base: Vec<MemoryValue> = (0..2500).collect();
+        let mut memory: Memory<B> = Memory::new(&base).with_backend();
+        assert_eq!(memory.base_len(), 2500);
+        assert_eq!(memory.get(0), 0);
+        assert_eq!(memory.get(2499), 2499);
+        assert_eq!(memory.get(2500), 0);
+        assert_eq!(memory.get(usize::MAX), 0);
+
+        memory.set(1500, -1);
+        memory.set(5000, 7);
+        memory.set(usize::MAX, 8);
+        assert_eq!(memory.get(1500), -1);
+        assert_eq!(memory.get(5000), 7);
+        assert_eq!(memory.get(5001), 0);
+        assert_eq!(memory.get(usize::MAX), 8);
+        assert!(memory.is_allocated(2499));
+        assert!(memory.is_allocated(5000));
+        assert!(!memory.is_allocated(usize::MAX - 1));
+        assert_eq!(memory.base().count(), 2500);
+        assert_eq!(memory.base().nth(1500), Some(-1));
+
+        let mut values: Vec<_> = memory.additional().values().collect();
+        values.sort_unstable();
+        assert_eq!(values, [(5000, 7), (usize::MAX, 8)]);
+    }
+
+    #[test]
+    fn sparse() {
+        reads_and_writes::<SparseMemory>();
+
+        let mut memory: Memory<SparseMemory> = Memory::new(&[]).with_backend();
+        memory.set(5000, 7);
+        assert_eq!(memory.allocated(), 1);
+        assert!(!memory.is_allocated(5001));
+        assert_eq!(memory.allocation_size(5001), 1);
+    }
+
+    #[test]
+    fn paged() {
+        reads_and_writes::<PagedMemory>();
+
+        let mut memory = Memory::new(&[]);
+        memory.set(5000, 7);
+        assert_eq!(memory.allocated(), PAGE_SIZE);
+        assert!(memory.is_allocated(5001));
+        assert!(!memory.is_allocated(6144));
+        assert_eq!(memory.allocation_size(6144), PAGE_SIZE);
+        assert_eq!(memory.allocation_size(usize::MAX - 1), 1);
+    }
+
+    #[test]
+    fn copy_on_write() {
+        let base: Vec<MemoryValue> = (0..2500).collect();
+        let mut original = Memory::new(&base);
+        original.set(5000, 7);
+        let mut copy = original.clone();
+        assert!(original
+            .pages
+            .iter()
+            .zip(&copy.pages)
+            .all(|(a, b)| Arc::ptr_eq(a, b)));
+
+        copy.set(1500, -1);
+        copy.set(5000, 8);
+        assert_eq!(original.get(1500), 1500);
+        assert_eq!(copy.get(1500), -1);
+        assert_eq!(original.get(5000), 7);
+        assert_eq!(copy.get(5000), 8);
+        assert!(Arc::ptr_eq(&original.pages[0], &copy.pages[0]));
+        assert!(!Arc::ptr_eq(&original.pages[1], &copy.pages[1]));
+        assert!(Arc::ptr_eq(&original.pages[2], &copy.pages[2]));
+    }
+}
@@ -0,0 +1,406 @@
+use crate::{IntCodeComputer, MemoryValue, NetworkError, Program, Status};
+use std::{collections::VecDeque, convert::TryFrom};
+
+/// Where a machine in a [`Network`] sends its output.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum Endpoint {
+    /// The input queue of the machine with the given id.
+    Machine(usize),
+    /// The network's own output, read with [`Network::output`].
+    External,
+}
+
+/// Why a [`Network`] stopped running.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum NetworkStatus {
+    /// Every machine has halted.
+    Halted,
+    /// Every machine that hasn't halted is waiting on input that nothing in
+    /// the network can provide. Send more input to resume.
+    Blocked,
+}
+
+struct Node {
+    computer: IntCodeComputer,
+    destinations: Vec<Endpoint>,
+    halted: bool,
+}
+
+/// Several machines whose outputs are wired directly into each other's
+/// inputs, such as a chain of amplifiers or a feedback loop.
+#[derive(Default)]
+pub struct Network {
+    nodes: Vec<Node>,
+    output: Vec<MemoryValue>,
+}
+
+impl Network {
+    pub fn new() -> Self {
+        Network::default()
+    }
+
+    /// Creates a chain of machines running `program`, each first given its own
+    /// setting. Each machine's output is sent to the next machine, and the
+    /// last machine's output is sent to the network's output.
+    pub fn chain(program: &Program, settings: &[MemoryValue]) -> Self {
+        let mut network = Network::new();
+        for &setting in settings {
+            let id = network.add(IntCodeComputer::new(program));
+            network.send(id, setting);
+            if id > 0 {
+                network.connect(id - 1, Endpoint::Machine(id));
+            }
+        }
+
+        if let Some(last) = settings.len().checked_sub(1) {
+            network.connect(last, Endpoint::External);
+        }
+
+        network
+    }
+
+    /// Creates a chain of machines like [`Network::chain`], and additionally
+    /// feeds the last machine's output back into the first machine.
+    pub fn feedback_loop(program: &Program, settings: &[MemoryValue]) -> Self {
+        let mut network = Network::chain(program, settings);
+        if let Some(last) = settings.len().checked_sub(1) {
+            network.connect(last, Endpoint::Machine(0));
+        }
+
+        network
+    }
+
+    /// Adds a machine to the network, returning its id.
+    pub fn add(&mut self, computer: IntCodeComputer) -> usize {
+        self.nodes.push(Node {
+            computer,
+            destinations: Vec::new(),
+            halted: false,
+        });
+        self.nodes.len() - 1
+    }
+
+    /// Sends the output of machine `from` to `to`. A machine may be connected
+    /// to several endpoints, each of which receives every value it outputs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `from` or the machine `to` sends to isn't in the network.
+    pub fn connect(&mut self, from: usize, to: Endpoint) {
+        if let Endpoint::Machine(id) = to {
+            assert!(id < self.nodes.len(), "no machine with id {}", id);
+        }
+        self.nodes[from].destinations.push(to);
+    }
+
+    /// Queues a value as input to machine `to`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `to` isn't in the network.
+    pub fn send(&mut self, to: usize, value: MemoryValue) {
+        self.nodes[to].computer.provide_input(value);
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `id` isn't in the network.
+    pub fn machine(&self, id: usize) -> &IntCodeComputer {
+        &self.nodes[id].computer
+    }
+
+    /// Values sent to [`Endpoint::External`] so far.
+    pub fn output(&self) -> &[MemoryValue] {
+        &self.output
+    }
+
+    /// Runs each machine in turn until every machine has halted or is blocked
+    /// waiting for input.
+    pub fn run(&mut self) -> Result<NetworkStatus, NetworkError> {
+        loop {
+            for id in 0..self.nodes.len() {
+                if self.nodes[id].halted {
+                    continue;
+                }
+
+                loop {
+                    let status = self.nodes[id]
+                        .computer
+                        .run()
+                        .map_err(|error| NetworkError::Machine { id, error })?;
+                    match status {
+                        Status::Output(value) => {
+                            for i in 0..self.nodes[id].destinations.len() {
+                                match self.nodes[id].destinations[i] {
+                                    Endpoint::Machine(to) => self.send(to, value),
+                                    Endpoint::External => self.output.push(value),
+                                }
+                            }
+                        }
+                        Status::NeedsInput => break,
+                        Status::Halted => {
+                            self.nodes[id].halted = true;
+                            break;
+                        }
+                    }
+                }
+            }
+
+            let mut running = self.nodes.iter().filter(|node| !node.halted).peekable();
+            if running.peek().is_none() {
+                return Ok(NetworkStatus::Halted);
+            }
+            if running.all(|node| node.computer.pending_input().is_empty()) {
+                return Ok(NetworkStatus::Blocked);
+            }
+        }
+    }
+}
+
+/// A packet sent between machines in a [`PacketNetwork`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct Packet {
+    pub destination: MemoryValue,
+    pub x: MemoryValue,
+    pub y: MemoryValue,
+}
+
+/// A network of machines that communicate by sending addressed packets.
+///
+/// Each machine is first given its address as input. Machines send a packet
+/// by writing its destination, X and Y values, and receive packets as X and Y
+/// values. A machine that reads input while no packets are queued for it
+/// receives -1.
+pub struct PacketNetwork {
+    machines: Vec<IntCodeComputer>,
+    queues: Vec<VecDeque<Packet>>,
+    partial: Vec<Vec<MemoryValue>>,
+    idle: bool,
+}
+
+impl PacketNetwork {
+    /// Creates a network of `size` machines running `program`, addressed from
+    /// 0.
+    pub fn new(program: &Program, size: usize) -> Self {
+        let machines = (0..size)
+            .map(|address| {
+                let mut computer = IntCodeComputer::new(program);
+                computer.provide_input(address as MemoryValue);
+                computer
+            })
+            .collect();
+
+        PacketNetwork {
+            machines,
+            queues: vec![VecDeque::new(); size],
+            partial: vec![Vec::with_capacity(3); size],
+            idle: false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.machines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.machines.is_empty()
+    }
+
+    /// # Panics
+    ///
+    /// Panics if there's no machine with the given address.
+    pub fn machine(&self, address: usize) -> &IntCodeComputer {
+        &self.machines[address]
+    }
+
+    /// Queues a packet for the machine at its destination address.
+    pub fn send(&mut self, packet: Packet) -> Result<(), NetworkError> {
+        match usize::try_from(packet.destination) {
+            Ok(address) if address < self.machines.len() => {
+                self.queues[address].push_back(packet);
+                self.idle = false;
+                Ok(())
+            }
+            _ => Err(NetworkError::UnknownAddress(packet.destination)),
+        }
+    }
+
+    /// Gives each machine a turn to send and receive packets, returning the
+    /// packets sent to addresses outside the network.
+    ///
+    /// During its turn, a machine runs until it reads input while its queue is
+    /// empty for the second time, so each machine polls at most once per
+    /// turn.
+    pub fn step(&mut self) -> Result<Vec<Packet>, NetworkError> {
+        let mut external = Vec::new();
+        let mut idle = true;
+        for address in 0..self.machines.len() {
+            let mut polled = false;
+            loop {
+                let status = self.machines[address]
+                    .run()
+                    .map_err(|error| NetworkError::Machine { id: address, error })?;
+                match status {
+                    Status::Output(value) => {
+                        self.partial[address].push(value);
+                        if let [destination, x, y] = self.partial[address][..] {
+                            self.partial[address].clear();
+                            idle = false;
+
+                            let packet = Packet { destination, x, y };
+                            match usize::try_from(destination) {
+                                Ok(to) if to < self.machines.len() => {
+                                    self.queues[to].push_back(packet)
+                                }
+                                _ => external.push(packet),
+                            }
+                        }
+                    }
+                    Status::NeedsInput => match self.queues[address].pop_front() {
+                        Some(packet) => {
+                            idle = false;
+                            self.machines[address].provide_input(packet.x);
+                            self.machines[address].provide_input(packet.y);
+                        }
+                        None if !polled => {
+                            polled = true;
+                            self.machines[address].provide_input(-1);
+                        }
+                        None => break,
+                    },
+                    Status::Halted => break,
+                }
+            }
+        }
+
+        self.idle = idle && self.queues.iter().all(VecDeque::is_empty);
+        Ok(external)
+    }
+
+    /// Whether the last turn passed without any machine sending or receiving
+    /// a packet.
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chain() {
+        let program = Program::new(&[
+            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+        ]);
+        let mut network = Network::chain(&program, &[4, 3, 2, 1, 0]);
+        network.send(0, 0);
+        assert_eq!(network.run().unwrap(), NetworkStatus::Halted);
+        assert_eq!(network.output(), [43210]);
+    }
+
+    #[test]
+    fn feedback_loop() {
+        let program = Program::new(&[
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ]);
+        let mut network = Network::feedback_loop(&program, &[9, 8, 7, 6, 5]);
+        network.send(0, 0);
+        assert_eq!(network.run().unwrap(), NetworkStatus::Halted);
+        assert_eq!(network.output().last(), Some(&139629729));
+    }
+
+    #[test]
+    fn blocked() {
+        // adds pairs of inputs forever
+        let program = Program::new(&[3, 20, 3, 21, 1, 20, 21, 22, 4, 22, 1105, 1, 0]);
+        let mut network = Network::new();
+        let id = network.add(IntCodeComputer::new(&program));
+        network.connect(id, Endpoint::External);
+
+        network.send(id, 1);
+        assert_eq!(network.run().unwrap(), NetworkStatus::Blocked);
+        assert!(network.output().is_empty());
+
+        network.send(id, 2);
+        assert_eq!(network.run().unwrap(), NetworkStatus::Blocked);
+        assert_eq!(network.output(), [3]);
+    }
+
+    #[test]
+    fn faults() {
+        // the second machine runs an unknown opcode after reading its input
+        let mut network = Network::new();
+        let first = network.add(IntCodeComputer::new(&Program::new(&[104, 1, 99])));
+        let second = network.add(IntCodeComputer::new(&Program::new(&[3, 5, 4, 5, 42, 0])));
+        network.connect(first, Endpoint::Machine(second));
+        let error = network.run().unwrap_err();
+        match &error {
+            NetworkError::Machine { id: 1, error } => assert_eq!(error.ip, 4),
+            error => panic!("unexpected error: {}", error),
+        }
+        assert_eq!(
+            error.to_string(),
+            "machine 1: unknown opcode 42 at 4 (instruction 42)"
+        );
+    }
+
+    #[test]
+    fn packets() {
+        // sends (0, 7) or (1, 7) to the other machine, then forwards every
+        // packet it receives to address 255
+        let program = Program::new(&[
+            3, 60, 1001, 60, 1, 61, 1008, 61, 2, 62, 1006, 62, 17, 1101, 0, 0, 61, 4, 61, 4, 60,
+            104, 7, 3, 63, 1008, 63, -1, 64, 1005, 64, 23, 3, 65, 104, 255, 4, 63, 4, 65, 1105, 1,
+            23,
+        ]);
+        let mut network = PacketNetwork::new(&program, 2);
+        let mut external = Vec::new();
+        while !network.is_idle() {
+            external.extend(network.step().unwrap());
+        }
+
+        external.sort_by_key(|packet| packet.x);
+        assert_eq!(
+            external,
+            [
+                Packet {
+                    destination: 255,
+                    x: 0,
+                    y: 7
+                },
+                Packet {
+                    destination: 255,
+                    x: 1,
+                    y: 7
+                },
+            ]
+        );
+
+        let packet = Packet {
+            destination: 1,
+            x: 5,
+            y: 6,
+        };
+        network.send(packet).unwrap();
+        assert!(!network.is_idle());
+        assert_eq!(
+            network.step().unwrap(),
+            [Packet {
+                destination: 255,
+                ..packet
+            }]
+        );
+        assert!(network.step().unwrap().is_empty());
+        assert!(network.is_idle());
+
+        assert!(matches!(
+            network.send(Packet {
+                destination: 2,
+                ..packet
+            }),
+            Err(NetworkError::UnknownAddress(2))
+        ));
+    }
+}
@@ -0,0 +1,353 @@
+use crate::{Instruction, MemoryValue, Opcode, TraceEntry, TraceSink};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display, Formatter},
+    io::Write,
+};
+
+/// How many rows of each table [`Profile`]'s `Display` implementation shows.
+const TABLE_ROWS: usize = 10;
+
+/// How often a basic block ran.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct BlockStats {
+    pub start: usize,
+    /// The number of times the block was entered.
+    pub executions: u64,
+    /// The number of instructions executed in the block.
+    pub steps: u64,
+}
+
+/// A function call the program made, inferred from its control flow.
+#[derive(Clone, Copy, Debug)]
+struct Frame {
+    entry: usize,
+    return_addr: usize,
+}
+
+/// Statistics about the instructions a program executed, collected by
+/// recording it with a [`Tracer`](crate::Tracer).
+///
+/// Blocks are counted from the first instruction, a jump target or the
+/// instruction after a jump, up to the next jump or halt. Calls are inferred
+/// the same way as by [`ControlFlowGraph`](crate::ControlFlowGraph): a jump
+/// taken right after the program writes the address following it is a call,
+/// and a jump to the return address of a call on the stack returns from it.
+/// Custom opcodes aren't recorded by the tracer, so they aren't counted.
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+    base_len: usize,
+    steps: u64,
+    opcodes: HashMap<Opcode, u64>,
+    addresses: HashMap<usize, (Instruction, u64)>,
+    blocks: HashMap<usize, BlockStats>,
+    /// The start of the block being executed, if it hasn't ended.
+    block: Option<usize>,
+    touched: HashSet<usize>,
+    peak_relative_base: Option<i64>,
+    stack: Vec<Frame>,
+    /// Instructions executed with each stack of function entries.
+    stacks: HashMap<Vec<usize>, u64>,
+    /// The value written by the last instruction, if it wrote one.
+    last_write: Option<MemoryValue>,
+}
+
+impl Profile {
+    /// Creates an empty profile of a program with `base_len` values in its base
+    /// memory.
+    pub fn new(base_len: usize) -> Self {
+        Profile {
+            base_len,
+            ..Profile::default()
+        }
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// The number of times each opcode was executed, most executed first.
+    pub fn opcode_counts(&self) -> Vec<(Opcode, u64)> {
+        let mut counts: Vec<_> = self
+            .opcodes
+            .iter()
+            .map(|(&opcode, &count)| (opcode, count))
+            .collect();
+        counts.sort_by_key(|&(opcode, count)| (std::cmp::Reverse(count), opcode.value()));
+        counts
+    }
+
+    /// The addresses of the `n` most executed instructions and how often each
+    /// was executed, most executed first.
+    pub fn hottest_addresses(&self, n: usize) -> Vec<(usize, u64)> {
+        let mut counts: Vec<_> = self
+            .addresses
+            .iter()
+            .map(|(&addr, &(_, count))| (addr, count))
+            .collect();
+        counts.sort_by_key(|&(addr, count)| (std::cmp::Reverse(count), addr));
+        counts.truncate(n);
+        counts
+    }
+
+    /// The `n` blocks that executed the most instructions.
+    pub fn hottest_blocks(&self, n: usize) -> Vec<BlockStats> {
+        let mut blocks: Vec<_> = self.blocks.values().copied().collect();
+        blocks.sort_by_key(|block| (std::cmp::Reverse(block.steps), block.start));
+        blocks.truncate(n);
+        blocks
+    }
+
+    /// The number of distinct cells read or written in the base memory and
+    /// past it.
+    pub fn memory_touched(&self) -> (usize, usize) {
+        let base = self
+            .touched
+            .iter()
+            .filter(|&&addr| addr < self.base_len)
+            .count();
+        (base, self.touched.len() - base)
+    }
+
+    /// The highest the relative base has been, if any instructions have been
+    /// recorded.
+    pub fn peak_relative_base(&self) -> Option<i64> {
+        self.peak_relative_base
+    }
+
+    /// Writes the number of instructions executed in each inferred stack of
+    /// calls in the folded format read by flamegraph tools, like
+    /// `main;fn_922;fn_922 1234`. Functions are named like in
+    /// [`decompile`](crate::decompile)'s output.
+    pub fn write_folded<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        let mut stacks: Vec<_> = self
+            .stacks
+            .iter()
+            .map(|(stack, &count)| {
+                let names = stack.iter().map(|&entry| format!("fn_{}", entry));
+                let names: Vec<_> = std::iter::once("main".to_string()).chain(names).collect();
+                (names.join(";"), count)
+            })
+            .collect();
+        stacks.sort();
+
+        for (stack, count) in stacks {
+            writeln!(writer, "{} {}", stack, count)?;
+        }
+        writer.flush()
+    }
+
+    fn share(&self, count: u64) -> f64 {
+        count as f64 * 100.0 / self.steps.max(1) as f64
+    }
+
+    /// Updates the call stack for a taken jump from `ip` to `target`.
+    fn jump(&mut self, ip: usize, target: usize) {
+        let return_addr = ip + 3;
+        if self.last_write == Some(return_addr as MemoryValue) {
+            self.stack.push(Frame {
+                entry: target,
+                return_addr,
+            });
+        } else if let Some(depth) = self
+            .stack
+            .iter()
+            .rposition(|frame| frame.return_addr == target)
+        {
+            self.stack.truncate(depth);
+        }
+    }
+}
+
+impl TraceSink for Profile {
+    fn record(&mut self, entry: &TraceEntry) -> anyhow::Result<()> {
+        let opcode = entry.instruction.opcode;
+        self.steps += 1;
+        *self.opcodes.entry(opcode).or_insert(0) += 1;
+        self.addresses
+            .entry(entry.ip)
+            .or_insert((entry.instruction, 0))
+            .1 += 1;
+
+        let start = *self.block.get_or_insert(entry.ip);
+        let block = self.blocks.entry(start).or_insert(BlockStats {
+            start,
+            ..BlockStats::default()
+        });
+        if start == entry.ip {
+            block.executions += 1;
+        }
+        block.steps += 1;
+
+        self.touched.extend(
+            entry
+                .reads
+                .iter()
+                .chain(&entry.write)
+                .map(|access| access.addr),
+        );
+        let relative_base = entry.new_relative_base.unwrap_or(entry.relative_base);
+        let peak = self.peak_relative_base.get_or_insert(entry.relative_base);
+        *peak = relative_base.max(*peak);
+
+        let frames = self.stack.iter().map(|frame| frame.entry).collect();
+        *self.stacks.entry(frames).or_insert(0) += 1;
+
+        match opcode {
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+                self.block = None;
+                let condition = entry.reads[0].value != 0;
+                if condition == (opcode == Opcode::JumpIfTrue) {
+                    self.jump(entry.ip, entry.reads[1].value as usize);
+                }
+            }
+            Opcode::Halt => self.block = None,
+            _ => {}
+        }
+        self.last_write = entry.write.map(|access| access.value);
+
+        Ok(())
+    }
+}
+
+impl Display for Profile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "instructions executed: {}", self.steps)?;
+
+        writeln!(f, "\n{:<8} {:>12} {:>7}", "opcode", "count", "share")?;
+        for (opcode, count) in self.opcode_counts() {
+            writeln!(
+                f,
+                "{:<8} {:>12} {:>6.1}%",
+                opcode.mnemonic(),
+                count,
+                self.share(count)
+            )?;
+        }
+
+        writeln!(
+            f,
+            "\n{:>8} {:<8} {:>12} {:>7}",
+            "address", "opcode", "count", "share"
+        )?;
+        for (addr, count) in self.hottest_addresses(TABLE_ROWS) {
+            let opcode = self.addresses[&addr].0.opcode;
+            writeln!(
+                f,
+                "{:>8} {:<8} {:>12} {:>6.1}%",
+                addr,
+                opcode.mnemonic(),
+                count,
+                self.share(count)
+            )?;
+        }
+
+        writeln!(
+            f,
+            "\n{:>8} {:>12} {:>13} {:>7}",
+            "block", "executions", "instructions", "share"
+        )?;
+        for block in self.hottest_blocks(TABLE_ROWS) {
+            writeln!(
+                f,
+                "{:>8} {:>12} {:>13} {:>6.1}%",
+                block.start,
+                block.executions,
+                block.steps,
+                self.share(block.steps)
+            )?;
+        }
+
+        let (base, additional) = self.memory_touched();
+        writeln!(
+            f,
+            "\nmemory touched: {} of {} cells in base memory, {} past it",
+            base, self.base_len, additional
+        )?;
+        if let Some(peak) = self.peak_relative_base {
+            writeln!(f, "peak relative base: {}", peak)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assemble, IntCodeComputer, Program, Status, Tracer};
+
+    /// Calls a function that adds 5 to a value on the stack and writes it,
+    /// once for each count down from its input.
+    const CALLS: &str = "
+                ARB #100
+                IN -> [n]
+        loop:   ADD #0, #back -> [rb+0]
+                JT #1, #add
+        back:   ADD [n], #-1 -> [n]
+                JT [n], #loop
+                HLT
+        add:    ADD [rb+1], #5 -> [rb+1]
+                OUT [rb+1]
+                JF #0, [rb+0]
+        n:      DATA 0
+    ";
+
+    fn profile(input: MemoryValue) -> Profile {
+        let program = Program::from(assemble(CALLS).unwrap());
+        let mut computer = IntCodeComputer::new(&program);
+        computer.provide_input(input);
+
+        let mut tracer = Tracer::new(computer, Profile::new(program.len()));
+        let mut output = Vec::new();
+        while let Status::Output(value) = tracer.run().unwrap() {
+            output.push(value);
+        }
+        assert_eq!(output, [5, 10, 15]);
+        tracer.into_parts().1
+    }
+
+    #[test]
+    fn counts() {
+        let profile = profile(3);
+        assert_eq!(profile.steps(), 24);
+        assert_eq!(
+            profile.opcode_counts()[..3],
+            [
+                (Opcode::Add, 9),
+                (Opcode::JumpIfTrue, 6),
+                (Opcode::Output, 3)
+            ]
+        );
+        assert_eq!(profile.hottest_addresses(2), [(4, 3), (8, 3)]);
+
+        let blocks: Vec<_> = profile
+            .hottest_blocks(10)
+            .iter()
+            .map(|block| (block.start, block.executions, block.steps))
+            .collect();
+        assert_eq!(
+            blocks,
+            [(19, 3, 9), (11, 3, 6), (0, 1, 4), (4, 2, 4), (18, 1, 1)]
+        );
+
+        assert_eq!(profile.memory_touched(), (10, 2));
+        assert_eq!(profile.peak_relative_base(), Some(100));
+
+        let table = profile.to_string();
+        assert!(table.starts_with("instructions executed: 24\n"));
+        assert!(table.contains("\nADD                 9   37.5%\n"));
+        assert!(table.contains("\n      19            3             9   37.5%\n"));
+        assert!(table.contains("memory touched: 10 of 29 cells in base memory, 2 past it\n"));
+    }
+
+    #[test]
+    fn folded() {
+        let mut folded = Vec::new();
+        profile(3).write_folded(&mut folded).unwrap();
+        assert_eq!(
+            String::from_utf8(folded).unwrap(),
+            "main 15\nmain;fn_19 9\n"
+        );
+    }
+}
@@ -0,0 +1,42 @@
+use crate::{Memory, MemoryValue, PagedMemory, Word};
+
+/// A loaded Intcode program.
+///
+/// Machines created from the same program share its memory pages until they
+/// write to them, so starting many machines from one program is cheap.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Program<W: Word = MemoryValue> {
+    memory: Memory<PagedMemory<W>>,
+}
+
+impl<W: Word> Program<W> {
+    pub fn new(values: &[W]) -> Self {
+        Program {
+            memory: Memory::new(values),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.memory.base_len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn memory(&self) -> &Memory<PagedMemory<W>> {
+        &self.memory
+    }
+}
+
+impl<W: Word> From<&[W]> for Program<W> {
+    fn from(values: &[W]) -> Self {
+        Program::new(values)
+    }
+}
+
+impl<W: Word> From<Vec<W>> for Program<W> {
+    fn from(values: Vec<W>) -> Self {
+        Program::new(&values)
+    }
+}
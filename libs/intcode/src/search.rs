@@ -0,0 +1,147 @@
+use crate::{IntCodeComputer, MemoryBackend, Status};
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+/// How many instructions a candidate runs between checks for an earlier match.
+const CANCEL_INTERVAL: u64 = 1024;
+
+/// Searches for the first candidate, in order, for which the program halts
+/// with a machine that satisfies `predicate`.
+///
+/// Each candidate runs on a fork of `computer`, which `setup` prepares, for
+/// example by setting memory or providing input. The predicate is given the
+/// halted machine and everything it wrote. Candidates whose machine faults or
+/// waits for input don't match, so set limits on `computer` if a candidate
+/// could run forever.
+///
+/// Candidates are run on every core. Once a match is found, candidates after
+/// it are skipped or cancelled, but those before it still run, so the result
+/// is always the first match regardless of which thread finds what first.
+pub fn search<'a, T, B, S, P>(
+    computer: &IntCodeComputer<B>,
+    candidates: &'a [T],
+    setup: S,
+    predicate: P,
+) -> Option<&'a T>
+where
+    T: Sync,
+    B: MemoryBackend + Clone + Send + Sync,
+    S: Fn(&mut IntCodeComputer<B>, &T) + Sync,
+    P: Fn(&IntCodeComputer<B>, &[B::Word]) -> bool + Sync,
+{
+    let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+    search_with_threads(threads, computer, candidates, setup, predicate)
+}
+
+fn search_with_threads<'a, T, B, S, P>(
+    threads: usize,
+    computer: &IntCodeComputer<B>,
+    candidates: &'a [T],
+    setup: S,
+    predicate: P,
+) -> Option<&'a T>
+where
+    T: Sync,
+    B: MemoryBackend + Clone + Send + Sync,
+    S: Fn(&mut IntCodeComputer<B>, &T) + Sync,
+    P: Fn(&IntCodeComputer<B>, &[B::Word]) -> bool + Sync,
+{
+    // candidates are claimed in order, so every candidate before the first
+    // match is claimed and run before the search ends
+    let next = AtomicUsize::new(0);
+    let found = AtomicUsize::new(usize::MAX);
+
+    let matches = |index: usize| {
+        let mut machine = computer.clone();
+        setup(&mut machine, &candidates[index]);
+
+        let mut output = Vec::new();
+        loop {
+            if machine.steps() % CANCEL_INTERVAL == 0 && found.load(Ordering::Relaxed) < index {
+                return false;
+            }
+
+            match machine.step() {
+                Ok(None) => {}
+                Ok(Some(Status::Output(value))) => output.push(value),
+                Ok(Some(Status::Halted)) => return predicate(&machine, &output),
+                Ok(Some(Status::NeedsInput)) | Err(_) => return false,
+            }
+        }
+    };
+
+    thread::scope(|scope| {
+        for _ in 0..threads.min(candidates.len()) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                if index >= candidates.len() || index > found.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                if matches(index) {
+                    found.fetch_min(index, Ordering::Relaxed);
+                    break;
+                }
+            });
+        }
+    });
+
+    candidates.get(found.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assemble, Limits, Program};
+
+    /// Writes its input, then counts down from it in twos until it reaches 0,
+    /// which never happens for odd numbers.
+    const PARITY: &str = "
+                IN -> [n]
+                OUT [n]
+                ADD [n], #0 -> [left]
+        loop:   ADD [left], #-2 -> [left]
+                JT [left], #loop
+                HLT
+        n:      DATA 0
+        left:   DATA 0
+    ";
+
+    #[test]
+    fn first_match() {
+        let program = Program::from(assemble(PARITY).unwrap());
+        let mut computer = IntCodeComputer::new(&program);
+        computer.set_limits(Limits::none().with_max_steps(1_000));
+
+        // odd numbers and 0 run into the step limit
+        let candidates: Vec<_> = (0..200).rev().collect();
+        for &threads in &[1, 2, 7, 64] {
+            let found = search_with_threads(
+                threads,
+                &computer,
+                &candidates,
+                |machine, &n| machine.provide_input(n),
+                |_, output| output[0] % 3 == 0,
+            );
+            assert_eq!(found, Some(&198));
+
+            let found = search_with_threads(
+                threads,
+                &computer,
+                &candidates,
+                |machine, &n| machine.provide_input(n),
+                |machine, _| machine.get(16) < 40,
+            );
+            assert_eq!(found, Some(&38));
+        }
+
+        let none = search(&computer, &candidates, |_, _| {}, |_, _| true);
+        assert_eq!(none, None);
+        assert_eq!(
+            search(&computer, &[] as &[i64], |_, _| {}, |_, _| true),
+            None
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use crate::{LoadError, Memory, MemoryBackend, MemoryValue, PagedMemory};
+use std::{
+    collections::VecDeque,
+    convert::TryFrom,
+    fs::File,
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    path::Path,
+};
+
+const MAGIC: &[u8; 8] = b"\0INTSNAP";
+const VERSION: u32 = 1;
+
+/// The complete state of an [`IntCodeComputer`](crate::IntCodeComputer) at a
+/// point in its execution, including input it hasn't read yet.
+///
+/// Snapshots of machines working with `i64` words can be saved to a file and
+/// loaded later, even by another build. The file starts with the magic bytes
+/// `\0INTSNAP` and the format version, followed by the instruction pointer,
+/// relative base, step count, program image, the non-zero values past the
+/// program image and the pending input. Everything is little endian, the
+/// version is a `u32` and the rest are 64 bits.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Snapshot<B: MemoryBackend = PagedMemory> {
+    pub(crate) memory: Memory<B>,
+    pub(crate) relative_base: i64,
+    pub(crate) ip: usize,
+    pub(crate) input: VecDeque<B::Word>,
+    pub(crate) steps: u64,
+}
+
+impl<B: MemoryBackend> Snapshot<B> {
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+}
+
+fn write_u64<W: Write>(writer: &mut W, value: u64) -> std::io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn write_i64<W: Write>(writer: &mut W, value: i64) -> std::io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn read_bytes<R: Read, const N: usize>(reader: &mut R) -> Result<[u8; N], LoadError> {
+    let mut bytes = [0; N];
+    reader
+        .read_exact(&mut bytes)
+        .map_err(|error| match error.kind() {
+            ErrorKind::UnexpectedEof => LoadError::Truncated,
+            _ => LoadError::Io(error),
+        })?;
+    Ok(bytes)
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64, LoadError> {
+    Ok(u64::from_le_bytes(read_bytes(reader)?))
+}
+
+fn read_i64<R: Read>(reader: &mut R) -> Result<i64, LoadError> {
+    Ok(i64::from_le_bytes(read_bytes(reader)?))
+}
+
+fn read_usize<R: Read>(reader: &mut R) -> Result<usize, LoadError> {
+    // a value that doesn't fit can't be backed by enough data anyway
+    usize::try_from(read_u64(reader)?).map_err(|_| LoadError::Truncated)
+}
+
+impl<B: MemoryBackend<Word = MemoryValue>> Snapshot<B> {
+    /// Writes the snapshot in the format read by [`Snapshot::read_from`].
+    pub fn write_to<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        write_u64(&mut writer, self.ip as u64)?;
+        write_i64(&mut writer, self.relative_base)?;
+        write_u64(&mut writer, self.steps)?;
+
+        write_u64(&mut writer, self.memory.base_len() as u64)?;
+        for value in self.memory.base() {
+            write_i64(&mut writer, value)?;
+        }
+
+        let mut additional: Vec<_> = self.memory.additional().values().collect();
+        additional.sort_unstable();
+        write_u64(&mut writer, additional.len() as u64)?;
+        for (addr, value) in additional {
+            write_u64(&mut writer, addr as u64)?;
+            write_i64(&mut writer, value)?;
+        }
+
+        write_u64(&mut writer, self.input.len() as u64)?;
+        for &value in &self.input {
+            write_i64(&mut writer, value)?;
+        }
+        writer.flush()
+    }
+
+    /// Reads a snapshot written by [`Snapshot::write_to`].
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self, LoadError> {
+        match read_bytes::<_, 8>(&mut reader) {
+            Ok(magic) if magic == *MAGIC => {}
+            Ok(_) | Err(LoadError::Truncated) => return Err(LoadError::NotSnapshot),
+            Err(error) => return Err(error),
+        }
+        let version = u32::from_le_bytes(read_bytes(&mut reader)?);
+        if version != VERSION {
+            return Err(LoadError::UnsupportedVersion(version));
+        }
+
+        let ip = read_usize(&mut reader)?;
+        let relative_base = read_i64(&mut reader)?;
+        let steps = read_u64(&mut reader)?;
+
+        // lengths aren't trusted to preallocate, since the data may be cut off
+        let mut base = Vec::new();
+        for _ in 0..read_u64(&mut reader)? {
+            base.push(read_i64(&mut reader)?);
+        }
+        let mut memory = Memory::new(&base).with_backend::<B>();
+        for _ in 0..read_u64(&mut reader)? {
+            let addr = read_usize(&mut reader)?;
+            memory.set(addr, read_i64(&mut reader)?);
+        }
+
+        let mut input = VecDeque::new();
+        for _ in 0..read_u64(&mut reader)? {
+            input.push_back(read_i64(&mut reader)?);
+        }
+
+        if reader.read(&mut [0])? != 0 {
+            return Err(LoadError::TrailingData);
+        }
+
+        Ok(Snapshot {
+            memory,
+            relative_base,
+            ip,
+            input,
+            steps,
+        })
+    }
+
+    /// Saves the snapshot to a file, replacing it if it exists.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        self.write_to(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, LoadError> {
+        Snapshot::read_from(BufReader::new(File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IntCodeComputer, Program, SparseMemory, Status};
+
+    /// Counts up from its input, writing each value and storing it past the
+    /// end of the program.
+    const COUNTER: &[MemoryValue] = &[3, 15, 4, 15, 1001, 15, 1, 15, 1001, 15, 0, 5000, 1105, 1, 2];
+
+    fn saved<B: MemoryBackend<Word = MemoryValue>>() -> (IntCodeComputer<B>, Vec<u8>) {
+        let mut computer = IntCodeComputer::<B>::with_backend(&Program::new(COUNTER));
+        computer.provide_input(10);
+        computer.provide_input(20);
+        assert_eq!(computer.run().unwrap(), Status::Output(10));
+        assert_eq!(computer.run().unwrap(), Status::Output(11));
+
+        let mut bytes = Vec::new();
+        computer.snapshot().write_to(&mut bytes).unwrap();
+        (computer, bytes)
+    }
+
+    #[test]
+    fn round_trip() {
+        let (mut computer, bytes) = saved::<PagedMemory>();
+        let snapshot = Snapshot::<PagedMemory>::read_from(&bytes[..]).unwrap();
+        assert_eq!(snapshot.ip(), computer.ip());
+        assert_eq!(snapshot.steps(), computer.steps());
+
+        let mut restored = IntCodeComputer::from_snapshot(&snapshot);
+        assert_eq!(restored.get(5000), 11);
+        assert_eq!(restored.pending_input(), &[20]);
+        for _ in 0..3 {
+            assert_eq!(restored.run().unwrap(), computer.run().unwrap());
+        }
+
+        // the format doesn't depend on the backend
+        let (_, sparse) = saved::<SparseMemory>();
+        assert_eq!(sparse, bytes);
+        let snapshot = Snapshot::<SparseMemory>::read_from(&bytes[..]).unwrap();
+        assert_eq!(IntCodeComputer::from_snapshot(&snapshot).get(5000), 11);
+    }
+
+    #[test]
+    fn invalid() {
+        let (_, bytes) = saved::<PagedMemory>();
+        let read = |bytes: &[u8]| Snapshot::<PagedMemory>::read_from(bytes).unwrap_err();
+
+        assert!(matches!(read(b"1,2,3"), LoadError::NotSnapshot));
+        assert!(matches!(
+            read(b"\0INTCODE\x01\0\0\0"),
+            LoadError::NotSnapshot
+        ));
+        assert!(matches!(
+            read(&bytes[..bytes.len() - 1]),
+            LoadError::Truncated
+        ));
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(matches!(read(&trailing), LoadError::TrailingData));
+
+        let mut newer = bytes.clone();
+        newer[8] = 2;
+        assert!(matches!(read(&newer), LoadError::UnsupportedVersion(2)));
+
+        // a huge length must run out of data rather than allocate
+        let mut huge = bytes[..36].to_vec();
+        huge.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(read(&huge), LoadError::Truncated));
+    }
+}
@@ -0,0 +1,726 @@
+use crate::{
+    ErrorKind, Instruction, IntCodeComputer, IntcodeError, Limits, Memory, MemoryValue, Mode,
+    Opcode, Snapshot, Status,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::TryFrom,
+    fmt::{self, Display, Formatter},
+    ops::RangeInclusive,
+};
+
+/// A value computed by a [`SymbolicComputer`] from named unknowns.
+///
+/// Expressions built with [`Expr::checked_add`] and [`Expr::checked_mul`] are
+/// kept as a sum of distinct terms, each multiplied by a constant, with the
+/// constant term last. This keeps expressions that are linear in their symbols
+/// readable and lets [`Expr::solve`] solve them directly.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub enum Expr {
+    Const(MemoryValue),
+    Symbol(String),
+    Add(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    /// 1 if the first value is less than the second, otherwise 0.
+    LessThan(Box<Expr>, Box<Expr>),
+    /// 1 if the values are equal, otherwise 0.
+    Equals(Box<Expr>, Box<Expr>),
+    /// A value read from an address that depends on a symbol, which can't be
+    /// tracked.
+    Unknown,
+}
+
+type Term = (MemoryValue, Option<Expr>);
+
+impl Expr {
+    pub fn symbol(name: &str) -> Self {
+        Expr::Symbol(name.to_string())
+    }
+
+    /// The value of the expression, if it doesn't depend on any symbols.
+    pub fn as_const(&self) -> Option<MemoryValue> {
+        match self {
+            Expr::Const(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The terms of a sum, with `None` in place of the constant term's factor.
+    fn terms(self) -> Vec<Term> {
+        match self {
+            Expr::Const(value) => vec![(value, None)],
+            Expr::Add(a, b) => {
+                let mut terms = a.terms();
+                terms.extend(b.terms());
+                terms
+            }
+            Expr::Mul(a, b) => match (*a, *b) {
+                (Expr::Const(coefficient), factor) => vec![(coefficient, Some(factor))],
+                (a, b) => vec![(1, Some(Expr::Mul(Box::new(a), Box::new(b))))],
+            },
+            expr => vec![(1, Some(expr))],
+        }
+    }
+
+    /// Sums terms, combining those with the same factor. Returns `None` if a
+    /// coefficient overflows.
+    fn from_terms(terms: Vec<Term>) -> Option<Self> {
+        let mut combined: Vec<Term> = Vec::new();
+        for (coefficient, factor) in terms {
+            match combined.iter_mut().find(|(_, other)| *other == factor) {
+                Some((total, _)) => *total = total.checked_add(coefficient)?,
+                None => combined.push((coefficient, factor)),
+            }
+        }
+        combined.sort_by_key(|(_, factor)| factor.is_none());
+
+        let terms = combined
+            .into_iter()
+            .filter(|&(coefficient, _)| coefficient != 0)
+            .map(|(coefficient, factor)| match factor {
+                None => Expr::Const(coefficient),
+                Some(factor) if coefficient == 1 => factor,
+                Some(factor) => Expr::Mul(Box::new(Expr::Const(coefficient)), Box::new(factor)),
+            });
+        let sum = terms.fold(None, |sum, term| match sum {
+            Some(sum) => Some(Expr::Add(Box::new(sum), Box::new(term))),
+            None => Some(term),
+        });
+        Some(sum.unwrap_or(Expr::Const(0)))
+    }
+
+    /// Adds two expressions. Returns `None` if a constant overflows.
+    pub fn checked_add(self, other: Expr) -> Option<Self> {
+        if self == Expr::Unknown || other == Expr::Unknown {
+            return Some(Expr::Unknown);
+        }
+
+        let mut terms = self.terms();
+        terms.extend(other.terms());
+        Expr::from_terms(terms)
+    }
+
+    /// Multiplies two expressions. Returns `None` if a constant overflows.
+    pub fn checked_mul(self, other: Expr) -> Option<Self> {
+        match (self, other) {
+            (Expr::Const(0), _) | (_, Expr::Const(0)) => Some(Expr::Const(0)),
+            (Expr::Unknown, _) | (_, Expr::Unknown) => Some(Expr::Unknown),
+            (Expr::Const(k), expr) | (expr, Expr::Const(k)) => {
+                let terms = expr
+                    .terms()
+                    .into_iter()
+                    .map(|(coefficient, factor)| Some((coefficient.checked_mul(k)?, factor)))
+                    .collect::<Option<_>>()?;
+                Expr::from_terms(terms)
+            }
+            (a, b) => Some(Expr::Mul(Box::new(a), Box::new(b))),
+        }
+    }
+
+    pub fn less_than(self, other: Expr) -> Self {
+        match (self, other) {
+            (Expr::Unknown, _) | (_, Expr::Unknown) => Expr::Unknown,
+            (Expr::Const(a), Expr::Const(b)) => Expr::Const((a < b) as MemoryValue),
+            (a, b) if a == b => Expr::Const(0),
+            (a, b) => Expr::LessThan(Box::new(a), Box::new(b)),
+        }
+    }
+
+    pub fn equals(self, other: Expr) -> Self {
+        match (self, other) {
+            (Expr::Unknown, _) | (_, Expr::Unknown) => Expr::Unknown,
+            (Expr::Const(a), Expr::Const(b)) => Expr::Const((a == b) as MemoryValue),
+            (a, b) if a == b => Expr::Const(1),
+            (a, b) => Expr::Equals(Box::new(a), Box::new(b)),
+        }
+    }
+
+    /// Evaluates the expression with the given values for its symbols. Returns
+    /// `None` if a symbol has no value, the expression is unknown or the
+    /// result overflows.
+    pub fn eval(&self, values: &[(&str, MemoryValue)]) -> Option<MemoryValue> {
+        match self {
+            Expr::Const(value) => Some(*value),
+            Expr::Symbol(name) => values
+                .iter()
+                .find(|(symbol, _)| *symbol == name)
+                .map(|&(_, value)| value),
+            Expr::Add(a, b) => a.eval(values)?.checked_add(b.eval(values)?),
+            Expr::Mul(a, b) => a.eval(values)?.checked_mul(b.eval(values)?),
+            Expr::LessThan(a, b) => Some((a.eval(values)? < b.eval(values)?) as MemoryValue),
+            Expr::Equals(a, b) => Some((a.eval(values)? == b.eval(values)?) as MemoryValue),
+            Expr::Unknown => None,
+        }
+    }
+
+    /// The degree of the expression as a polynomial in a symbol, or `None` if
+    /// it isn't one, like when the symbol is compared.
+    fn degree(&self, symbol: &str) -> Option<u32> {
+        match self {
+            Expr::Const(_) | Expr::Unknown => Some(0),
+            Expr::Symbol(name) => Some((name == symbol) as u32),
+            Expr::Add(a, b) => Some(a.degree(symbol)?.max(b.degree(symbol)?)),
+            Expr::Mul(a, b) => Some(a.degree(symbol)? + b.degree(symbol)?),
+            Expr::LessThan(a, b) | Expr::Equals(a, b) => {
+                match (a.degree(symbol)?, b.degree(symbol)?) {
+                    (0, 0) => Some(0),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Finds values for symbols within the given ranges for which the
+    /// expression evaluates to `target`, returning them in the same order as
+    /// the ranges. If there are several solutions, the one with the lowest
+    /// value for the first symbol is returned, then the lowest for the second,
+    /// and so on.
+    ///
+    /// Every combination of values for all but the last symbol is tried, but
+    /// the last is solved for directly if the expression is linear in it.
+    /// The solution only says what the expression evaluates to, not whether
+    /// the program that computed it runs without faulting for those values.
+    pub fn solve(
+        &self,
+        target: MemoryValue,
+        ranges: &[(&str, RangeInclusive<MemoryValue>)],
+    ) -> Option<Vec<MemoryValue>> {
+        let mut values = Vec::with_capacity(ranges.len());
+        if self.solve_from(target, ranges, &mut values) {
+            Some(values.into_iter().map(|(_, value)| value).collect())
+        } else {
+            None
+        }
+    }
+
+    fn solve_from<'a>(
+        &self,
+        target: MemoryValue,
+        ranges: &[(&'a str, RangeInclusive<MemoryValue>)],
+        values: &mut Vec<(&'a str, MemoryValue)>,
+    ) -> bool {
+        let ((symbol, range), rest) = match ranges.split_first() {
+            Some(first) => first,
+            None => return self.eval(values) == Some(target),
+        };
+
+        if rest.is_empty() && matches!(self.degree(symbol), Some(0..=1)) {
+            if let Some(solution) = self.solve_linear(target, symbol, range, values) {
+                return match solution {
+                    Some(value) => {
+                        values.push((symbol, value));
+                        true
+                    }
+                    None => false,
+                };
+            }
+        }
+
+        for value in range.clone() {
+            values.push((symbol, value));
+            if self.solve_from(target, rest, values) {
+                return true;
+            }
+            values.pop();
+        }
+        false
+    }
+
+    /// Solves for the last symbol of an expression that's linear in it, given
+    /// values for the others. Returns `None` if it can't be solved directly.
+    fn solve_linear<'a>(
+        &self,
+        target: MemoryValue,
+        symbol: &'a str,
+        range: &RangeInclusive<MemoryValue>,
+        values: &mut Vec<(&'a str, MemoryValue)>,
+    ) -> Option<Option<MemoryValue>> {
+        let mut eval = |value| {
+            values.push((symbol, value));
+            let result = self.eval(values);
+            values.pop();
+            result
+        };
+
+        // the expression is a * symbol + b
+        let b = eval(0)?;
+        let a = eval(1)?.checked_sub(b)?;
+        let value = if a == 0 {
+            Some(*range.start()).filter(|_| b == target)
+        } else {
+            let difference = target.checked_sub(b)?;
+            let remainder = difference.checked_rem(a)?;
+            Some(difference.checked_div(a)?).filter(|_| remainder == 0)
+        };
+
+        // an overflow along the way can make the result wrong
+        Some(value.filter(|&value| range.contains(&value) && eval(value) == Some(target)))
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::LessThan(..) | Expr::Equals(..) => 0,
+            Expr::Add(..) => 1,
+            Expr::Mul(..) => 2,
+            Expr::Const(_) | Expr::Symbol(_) | Expr::Unknown => 3,
+        }
+    }
+}
+
+impl From<MemoryValue> for Expr {
+    fn from(value: MemoryValue) -> Self {
+        Expr::Const(value)
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // operands with lower precedence than the operator need parentheses
+        let operand = |f: &mut Formatter<'_>, expr: &Expr, precedence: u8| {
+            if expr.precedence() < precedence {
+                write!(f, "({})", expr)
+            } else {
+                write!(f, "{}", expr)
+            }
+        };
+
+        match self {
+            Expr::Const(value) => write!(f, "{}", value),
+            Expr::Symbol(name) => f.write_str(name),
+            Expr::Add(a, b) => {
+                operand(f, a, 1)?;
+                match **b {
+                    Expr::Const(value) if value < 0 => write!(f, " - {}", -(value as i128)),
+                    _ => {
+                        f.write_str(" + ")?;
+                        operand(f, b, 2)
+                    }
+                }
+            }
+            Expr::Mul(a, b) => {
+                operand(f, a, 2)?;
+                f.write_str(" * ")?;
+                operand(f, b, 3)
+            }
+            Expr::LessThan(a, b) => {
+                operand(f, a, 1)?;
+                f.write_str(" < ")?;
+                operand(f, b, 1)
+            }
+            Expr::Equals(a, b) => {
+                operand(f, a, 1)?;
+                f.write_str(" == ")?;
+                operand(f, b, 1)
+            }
+            Expr::Unknown => f.write_str("?"),
+        }
+    }
+}
+
+/// Runs an Intcode program with some of its values left as symbols, to find
+/// out how its results depend on them.
+///
+/// The machine starts in the state of an [`IntCodeComputer`], after which any
+/// memory cell or input can be given a symbolic value. Instructions work on
+/// [`Expr`]s rather than numbers, so the values the program computes from
+/// symbols are expressions. The program's control flow can't depend on a
+/// symbol, so using a symbolic value as an opcode, relative base offset or
+/// jump condition, or writing to a symbolic address, is a
+/// [`ErrorKind::SymbolicValue`] fault. Reading from a symbolic address gives
+/// [`Expr::Unknown`]. The step and address limits of the machine still apply.
+///
+/// Only the built-in opcodes are supported, always with
+/// [`Validation::Lenient`](crate::Validation::Lenient), and memory isn't
+/// limited. The machine's validation, allocation limit and custom opcodes are
+/// ignored, so the concrete machine may reject or fault on values solved for
+/// from the results. Run it with the solved values to check them.
+#[derive(Clone, Debug)]
+pub struct SymbolicComputer {
+    memory: Memory,
+    /// Cells holding values that aren't constants.
+    symbols: HashMap<usize, Expr>,
+    relative_base: i64,
+    ip: usize,
+    input: VecDeque<Expr>,
+    steps: u64,
+    limits: Limits,
+}
+
+impl SymbolicComputer {
+    /// Creates a machine in the current state of `computer`, including the
+    /// input it hasn't read yet.
+    pub fn new(computer: &IntCodeComputer) -> Self {
+        let Snapshot {
+            memory,
+            relative_base,
+            ip,
+            input,
+            steps,
+        } = computer.snapshot();
+        SymbolicComputer {
+            memory,
+            symbols: HashMap::new(),
+            relative_base,
+            ip,
+            input: input.into_iter().map(Expr::Const).collect(),
+            steps,
+            limits: *computer.limits(),
+        }
+    }
+
+    /// Queues a value to be consumed by the next input instruction.
+    pub fn provide_input(&mut self, value: Expr) {
+        self.input.push_back(value);
+    }
+
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    pub fn relative_base(&self) -> i64 {
+        self.relative_base
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn get(&self, addr: usize) -> Expr {
+        match self.symbols.get(&addr) {
+            Some(expr) => expr.clone(),
+            None => Expr::Const(self.memory.get(addr)),
+        }
+    }
+
+    pub fn set(&mut self, addr: usize, value: Expr) {
+        match value {
+            Expr::Const(value) => {
+                self.symbols.remove(&addr);
+                self.memory.set(addr, value);
+            }
+            value => {
+                self.symbols.insert(addr, value);
+            }
+        }
+    }
+
+    fn fault(&self, parameter: Option<usize>, kind: ErrorKind) -> IntcodeError {
+        IntcodeError {
+            ip: self.ip,
+            instruction: self.get(self.ip).as_const().unwrap_or_default(),
+            parameter,
+            kind,
+        }
+    }
+
+    fn concrete(&self, value: Expr, parameter: Option<usize>) -> Result<MemoryValue, IntcodeError> {
+        value
+            .as_const()
+            .ok_or_else(|| self.fault(parameter, ErrorKind::SymbolicValue))
+    }
+
+    fn to_address(&self, parameter: usize, value: MemoryValue) -> Result<usize, IntcodeError> {
+        let addr = usize::try_from(value)
+            .map_err(|_| self.fault(Some(parameter), ErrorKind::NegativeAddress(value)))?;
+        match self.limits.max_address {
+            Some(max_address) if addr >= max_address => {
+                Err(self.fault(Some(parameter), ErrorKind::AddressOutOfRange(addr)))
+            }
+            _ => Ok(addr),
+        }
+    }
+
+    /// Resolves the address a parameter refers to, or `None` if it depends on
+    /// a symbol.
+    fn parameter_address(
+        &self,
+        mode: Mode,
+        parameter: usize,
+    ) -> Result<Option<usize>, IntcodeError> {
+        let addr = self.ip + 1 + parameter;
+        let value = match (mode, self.get(addr)) {
+            (Mode::Immediate, _) => return Ok(Some(addr)),
+            (Mode::Position, Expr::Const(value)) => value,
+            (Mode::Relative, Expr::Const(offset)) => self
+                .relative_base
+                .checked_add(offset)
+                .ok_or_else(|| self.fault(Some(parameter), ErrorKind::Overflow))?,
+            _ => return Ok(None),
+        };
+        self.to_address(parameter, value).map(Some)
+    }
+
+    fn read(&self, mode: Mode, parameter: usize) -> Result<Expr, IntcodeError> {
+        match self.parameter_address(mode, parameter)? {
+            Some(addr) => Ok(self.get(addr)),
+            None => Ok(Expr::Unknown),
+        }
+    }
+
+    fn output_address(&self, mode: Mode, parameter: usize) -> Result<usize, IntcodeError> {
+        self.parameter_address(mode, parameter)?
+            .ok_or_else(|| self.fault(Some(parameter), ErrorKind::SymbolicValue))
+    }
+
+    fn jump_target(&self, mode: Mode, parameter: usize) -> Result<usize, IntcodeError> {
+        let target = self.concrete(self.read(mode, parameter)?, Some(parameter))?;
+        self.to_address(parameter, target)
+    }
+
+    /// Executes a single instruction like [`IntCodeComputer::step`].
+    pub fn step(&mut self) -> Result<Option<Status<Expr>>, IntcodeError> {
+        if let Some(max_steps) = self.limits.max_steps {
+            if self.steps >= max_steps {
+                return Err(self.fault(None, ErrorKind::StepLimitExceeded(max_steps)));
+            }
+        }
+
+        let value = self.concrete(self.get(self.ip), None)?;
+        let instruction = Instruction::decode(value).map_err(|kind| {
+            let parameter = match kind {
+                ErrorKind::UnknownMode { parameter, .. } => Some(parameter),
+                _ => None,
+            };
+            self.fault(parameter, kind)
+        })?;
+        let status = self.execute(instruction)?;
+        if !matches!(status, Some(Status::NeedsInput) | Some(Status::Halted)) {
+            self.steps += 1;
+        }
+
+        Ok(status)
+    }
+
+    fn execute(&mut self, instruction: Instruction) -> Result<Option<Status<Expr>>, IntcodeError> {
+        let [p1_mode, p2_mode, p3_mode] = instruction.modes;
+        match instruction.opcode {
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equals => {
+                let a = self.read(p1_mode, 0)?;
+                let b = self.read(p2_mode, 1)?;
+                let result = match instruction.opcode {
+                    Opcode::Add => a.checked_add(b),
+                    Opcode::Multiply => a.checked_mul(b),
+                    Opcode::LessThan => Some(a.less_than(b)),
+                    _ => Some(a.equals(b)),
+                };
+                let result = result.ok_or_else(|| self.fault(None, ErrorKind::Overflow))?;
+                let addr = self.output_address(p3_mode, 2)?;
+                self.set(addr, result);
+
+                self.ip += 4;
+            }
+            Opcode::Input => {
+                let addr = self.output_address(p1_mode, 0)?;
+                let input = match self.input.pop_front() {
+                    Some(input) => input,
+                    None => return Ok(Some(Status::NeedsInput)),
+                };
+                self.set(addr, input);
+
+                self.ip += 2;
+            }
+            Opcode::Output => {
+                let value = self.read(p1_mode, 0)?;
+
+                self.ip += 2;
+                return Ok(Some(Status::Output(value)));
+            }
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+                let condition = self.concrete(self.read(p1_mode, 0)?, Some(0))?;
+                let target = self.jump_target(p2_mode, 1)?;
+
+                if (condition != 0) == (instruction.opcode == Opcode::JumpIfTrue) {
+                    self.ip = target;
+                } else {
+                    self.ip += 3;
+                }
+            }
+            Opcode::AdjustRelativeBase => {
+                let offset = self.concrete(self.read(p1_mode, 0)?, Some(0))?;
+                self.relative_base = self
+                    .relative_base
+                    .checked_add(offset)
+                    .ok_or_else(|| self.fault(Some(0), ErrorKind::Overflow))?;
+
+                self.ip += 2;
+            }
+            Opcode::Halt => {
+                return Ok(Some(Status::Halted));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Runs the program until it halts, needs input, or writes output, like
+    /// [`IntCodeComputer::run`].
+    pub fn run(&mut self) -> Result<Status<Expr>, IntcodeError> {
+        loop {
+            if let Some(status) = self.step()? {
+                return Ok(status);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assemble, Program};
+
+    fn symbolic(source: &str) -> SymbolicComputer {
+        let program = Program::from(assemble(source).unwrap());
+        SymbolicComputer::new(&IntCodeComputer::new(&program))
+    }
+
+    #[test]
+    fn expressions() {
+        let x = Expr::symbol("x");
+        let y = Expr::symbol("y");
+
+        let expr = x.clone().checked_add(Expr::Const(3)).unwrap();
+        let expr = expr
+            .checked_mul(Expr::Const(2))
+            .unwrap()
+            .checked_add(x.clone())
+            .unwrap();
+        assert_eq!(expr.to_string(), "3 * x + 6");
+        assert_eq!(expr.eval(&[("x", 5)]), Some(21));
+        assert_eq!(expr.eval(&[("y", 5)]), None);
+
+        let expr = expr.checked_add(Expr::Const(-6)).unwrap();
+        assert_eq!(expr, x.clone().checked_mul(Expr::Const(3)).unwrap());
+        let expr = expr
+            .checked_add(y.clone().checked_mul(Expr::Const(-1)).unwrap())
+            .unwrap();
+        assert_eq!(
+            expr.clone().checked_add(y.clone()).unwrap().to_string(),
+            "3 * x"
+        );
+
+        let product = x
+            .clone()
+            .checked_add(Expr::Const(-1))
+            .unwrap()
+            .checked_mul(y)
+            .unwrap();
+        assert_eq!(product.to_string(), "(x - 1) * y");
+        let compared = product.less_than(x.clone()).equals(Expr::Const(0));
+        assert_eq!(compared.to_string(), "((x - 1) * y < x) == 0");
+        assert_eq!(compared.eval(&[("x", 3), ("y", 1)]), Some(0));
+
+        assert_eq!(x.clone().equals(x.clone()), Expr::Const(1));
+        assert_eq!(
+            Expr::Unknown.checked_mul(Expr::Const(0)),
+            Some(Expr::Const(0))
+        );
+        assert_eq!(x.checked_add(Expr::Unknown), Some(Expr::Unknown));
+        assert_eq!(
+            Expr::Const(MemoryValue::MAX).checked_add(Expr::Const(1)),
+            None
+        );
+    }
+
+    #[test]
+    fn solve() {
+        let a = Expr::symbol("a");
+        let b = Expr::symbol("b");
+        let linear = a
+            .clone()
+            .checked_mul(Expr::Const(100))
+            .unwrap()
+            .checked_add(b.clone())
+            .unwrap();
+        let ranges = [("a", 0..=99), ("b", 0..=99)];
+        assert_eq!(linear.solve(1234, &ranges), Some(vec![12, 34]));
+        assert_eq!(linear.solve(10_000, &ranges), None);
+
+        // the first solution is the one with the lowest values
+        let either = a.clone().checked_add(b.clone()).unwrap();
+        assert_eq!(either.solve(150, &ranges), Some(vec![51, 99]));
+        let ignored = a.clone().checked_add(Expr::Const(1)).unwrap();
+        assert_eq!(ignored.solve(8, &ranges), Some(vec![7, 0]));
+
+        // dividing by -1 can overflow, which falls back to searching
+        let negated = a.clone().checked_mul(Expr::Const(-1)).unwrap();
+        assert_eq!(negated.solve(-5, &ranges[..1]), Some(vec![5]));
+        assert_eq!(negated.solve(MemoryValue::MIN, &ranges[..1]), None);
+
+        // nonlinear expressions are searched
+        let square = b
+            .clone()
+            .checked_mul(b.clone())
+            .unwrap()
+            .checked_add(a)
+            .unwrap();
+        assert_eq!(square.solve(50, &ranges), Some(vec![1, 7]));
+        let compared = b.less_than(Expr::Const(10));
+        assert_eq!(compared.solve(0, &ranges), Some(vec![0, 10]));
+    }
+
+    #[test]
+    fn run() {
+        // computes (input * 3 + [a]) * [b] + 1
+        let mut computer = symbolic(
+            "
+                    IN -> [x]
+                    MUL [x], #3 -> [x]
+                    ADD [x], [a] -> [x]
+                    MUL [x], [b] -> [x]
+                    ADD [x], #1 -> [x]
+                    OUT [x]
+                    HLT
+            x:      DATA 0
+            a:      DATA 5
+            b:      DATA 2
+            ",
+        );
+        assert_eq!(computer.run().unwrap(), Status::NeedsInput);
+        computer.provide_input(Expr::symbol("n"));
+        computer.set(22, Expr::symbol("a"));
+
+        let output = match computer.run().unwrap() {
+            Status::Output(output) => output,
+            status => panic!("unexpected status {:?}", status),
+        };
+        assert_eq!(output.to_string(), "6 * n + 2 * a + 1");
+        assert_eq!(output, computer.get(21));
+        assert_eq!(computer.run().unwrap(), Status::Halted);
+        assert_eq!(
+            output.solve(43, &[("n", 0..=10), ("a", 0..=10)]),
+            Some(vec![4, 9])
+        );
+    }
+
+    #[test]
+    fn faults() {
+        let mut computer = symbolic(
+            "
+                    ADD [x], [x] -> [y]
+                    JT [x], #0
+            x:      DATA 0
+            y:      DATA 0
+            ",
+        );
+        computer.set(7, Expr::symbol("x"));
+        let error = computer.run().unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::SymbolicValue));
+        assert_eq!((error.ip, error.parameter), (4, Some(0)));
+        assert_eq!(computer.get(8).to_string(), "2 * x");
+
+        // reading from a symbolic address is fine, but writing isn't
+        let mut computer = symbolic(
+            "
+                    ADD [0], #0 -> [3]
+                    OUT [3]
+                    ADD #1, #1 -> [0]
+            ",
+        );
+        computer.set(1, Expr::symbol("addr"));
+        computer.set(9, Expr::symbol("addr"));
+        assert_eq!(computer.run().unwrap(), Status::Output(Expr::Unknown));
+        let error = computer.run().unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::SymbolicValue));
+        assert_eq!((error.ip, error.parameter), (6, Some(2)));
+    }
+}
@@ -0,0 +1,559 @@
+use crate::{Instruction, IntCodeComputer, IntcodeError, MemoryValue, Opcode, Status, TraceError};
+use anyhow::{bail, Context};
+use itertools::Itertools;
+use std::io::{Read, Write};
+
+const BINARY_MAGIC: &[u8; 4] = b"ICTR";
+const BINARY_VERSION: u8 = 2;
+
+/// A value read from or written to memory.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct Access {
+    pub addr: usize,
+    pub value: MemoryValue,
+}
+
+/// A record of a single executed instruction.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct TraceEntry {
+    /// The number of instructions executed before this one.
+    pub step: u64,
+    pub ip: usize,
+    pub instruction: Instruction,
+    /// The raw values of the instruction's parameters.
+    pub parameters: Vec<MemoryValue>,
+    /// The resolved address and value of each parameter the instruction
+    /// reads. Immediate parameters are read from their own address.
+    pub reads: Vec<Access>,
+    pub write: Option<Access>,
+    /// The relative base when the instruction was executed.
+    pub relative_base: i64,
+    /// The new relative base, if the instruction adjusted it.
+    pub new_relative_base: Option<i64>,
+}
+
+impl TraceEntry {
+    /// Writes the entry as a single line of JSON.
+    pub fn write_json<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        fn access(access: &Access) -> String {
+            format!("{{\"addr\":{},\"value\":{}}}", access.addr, access.value)
+        }
+
+        let modes = self.instruction.modes[..self.parameters.len()]
+            .iter()
+            .map(|mode| mode.value());
+        writeln!(
+            writer,
+            "{{\"step\":{},\"ip\":{},\"opcode\":\"{}\",\"modes\":[{}],\"parameters\":[{}],\
+             \"reads\":[{}],\"write\":{},\"relative_base\":{},\"new_relative_base\":{}}}",
+            self.step,
+            self.ip,
+            self.instruction.opcode,
+            modes.format(","),
+            self.parameters.iter().format(","),
+            self.reads.iter().map(access).format(","),
+            self.write
+                .as_ref()
+                .map_or_else(|| "null".to_string(), access),
+            self.relative_base,
+            self.new_relative_base
+                .map_or_else(|| "null".to_string(), |base| base.to_string()),
+        )
+    }
+
+    /// Writes the entry as a binary record. Steps are written relative to
+    /// `next_step`, the step after the previous entry's, so consecutive entries
+    /// take a single byte for them.
+    fn write_binary<W: Write>(&self, writer: &mut W, next_step: u64) -> std::io::Result<()> {
+        write_signed(writer, self.step.wrapping_sub(next_step) as i64)?;
+        write_unsigned(writer, self.ip as u64)?;
+        write_signed(writer, self.instruction.encode())?;
+        for &parameter in &self.parameters {
+            write_signed(writer, parameter)?;
+        }
+        for read in &self.reads {
+            write_unsigned(writer, read.addr as u64)?;
+            write_signed(writer, read.value)?;
+        }
+        if let Some(write) = &self.write {
+            write_unsigned(writer, write.addr as u64)?;
+            write_signed(writer, write.value)?;
+        }
+        write_signed(writer, self.relative_base)?;
+        if let Some(base) = self.new_relative_base {
+            write_signed(writer, base)?;
+        }
+
+        Ok(())
+    }
+
+    fn read_binary<R: Read>(reader: &mut R, next_step: u64) -> anyhow::Result<Option<Self>> {
+        let step = match read_unsigned(reader)? {
+            Some(value) => next_step.wrapping_add(zigzag_decode(value) as u64),
+            None => return Ok(None),
+        };
+        let ip = read_unsigned(reader)?.context("unexpected end of trace")? as usize;
+
+        let instruction = Instruction::decode(read_value(reader)?)?;
+        let arity = instruction.opcode.arity();
+        let output = instruction.opcode.output_parameter();
+        let parameters = (0..arity)
+            .map(|_| read_value(reader))
+            .collect::<anyhow::Result<_>>()?;
+        let reads = (0..arity - output.iter().count())
+            .map(|_| read_access(reader))
+            .collect::<anyhow::Result<_>>()?;
+        let write = output.map(|_| read_access(reader)).transpose()?;
+        let relative_base = read_value(reader)?;
+        let new_relative_base = match instruction.opcode {
+            Opcode::AdjustRelativeBase => Some(read_value(reader)?),
+            _ => None,
+        };
+
+        Ok(Some(TraceEntry {
+            step,
+            ip,
+            instruction,
+            parameters,
+            reads,
+            write,
+            relative_base,
+            new_relative_base,
+        }))
+    }
+}
+
+// values are written as LEB128 varints, with signed values zigzag encoded
+fn write_unsigned<W: Write>(writer: &mut W, mut value: u64) -> std::io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return writer.write_all(&[byte]);
+        }
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn write_signed<W: Write>(writer: &mut W, value: i64) -> std::io::Result<()> {
+    write_unsigned(writer, ((value << 1) ^ (value >> 63)) as u64)
+}
+
+fn read_unsigned<R: Read>(reader: &mut R) -> anyhow::Result<Option<u64>> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        if reader.read(&mut byte)? == 0 {
+            if shift == 0 {
+                return Ok(None);
+            }
+            bail!("unexpected end of trace");
+        }
+
+        value |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+    }
+
+    bail!("invalid varint in trace")
+}
+
+fn zigzag_decode(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+fn read_value<R: Read>(reader: &mut R) -> anyhow::Result<MemoryValue> {
+    let value = read_unsigned(reader)?.context("unexpected end of trace")?;
+    Ok(zigzag_decode(value))
+}
+
+fn read_access<R: Read>(reader: &mut R) -> anyhow::Result<Access> {
+    let addr = read_unsigned(reader)?.context("unexpected end of trace")?;
+    Ok(Access {
+        addr: addr as usize,
+        value: read_value(reader)?,
+    })
+}
+
+/// Receives the entries recorded by a [`Tracer`].
+pub trait TraceSink {
+    fn record(&mut self, entry: &TraceEntry) -> anyhow::Result<()>;
+}
+
+impl TraceSink for Vec<TraceEntry> {
+    fn record(&mut self, entry: &TraceEntry) -> anyhow::Result<()> {
+        self.push(entry.clone());
+        Ok(())
+    }
+}
+
+/// Writes each entry as a line of JSON.
+pub struct JsonLinesWriter<W: Write>(pub W);
+
+impl<W: Write> TraceSink for JsonLinesWriter<W> {
+    fn record(&mut self, entry: &TraceEntry) -> anyhow::Result<()> {
+        entry.write_json(&mut self.0)?;
+        Ok(())
+    }
+}
+
+/// Writes entries in a compact binary format that can be read back with
+/// [`read_binary_trace`].
+pub struct BinaryTraceWriter<W: Write> {
+    writer: W,
+    started: bool,
+    next_step: u64,
+}
+
+impl<W: Write> BinaryTraceWriter<W> {
+    pub fn new(writer: W) -> Self {
+        BinaryTraceWriter {
+            writer,
+            started: false,
+            next_step: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> TraceSink for BinaryTraceWriter<W> {
+    fn record(&mut self, entry: &TraceEntry) -> anyhow::Result<()> {
+        if !self.started {
+            self.writer.write_all(BINARY_MAGIC)?;
+            self.writer.write_all(&[BINARY_VERSION])?;
+            self.started = true;
+        }
+
+        entry.write_binary(&mut self.writer, self.next_step)?;
+        self.next_step = entry.step.wrapping_add(1);
+        Ok(())
+    }
+}
+
+/// Reads a trace written by a [`BinaryTraceWriter`].
+pub fn read_binary_trace<R: Read>(mut reader: R) -> anyhow::Result<Vec<TraceEntry>> {
+    let mut header = [0; 5];
+    match reader.read_exact(&mut header) {
+        Ok(()) => {}
+        Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(Vec::new()),
+        Err(error) => return Err(error.into()),
+    }
+    if &header[..4] != BINARY_MAGIC {
+        bail!("not an intcode trace");
+    }
+    if header[4] != BINARY_VERSION {
+        bail!("unsupported trace version: {}", header[4]);
+    }
+
+    let mut entries: Vec<TraceEntry> = Vec::new();
+    loop {
+        let next_step = entries.last().map_or(0, |entry| entry.step.wrapping_add(1));
+        match TraceEntry::read_binary(&mut reader, next_step)? {
+            Some(entry) => entries.push(entry),
+            None => break,
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Finds the index of the first entry where two traces differ, if they do.
+pub fn first_divergence(a: &[TraceEntry], b: &[TraceEntry]) -> Option<usize> {
+    match a.iter().zip(b).position(|(a, b)| a != b) {
+        Some(index) => Some(index),
+        None if a.len() != b.len() => Some(a.len().min(b.len())),
+        None => None,
+    }
+}
+
+/// Runs an [`IntCodeComputer`], recording each instruction it executes.
+///
+/// A [`TraceEntry`] can only describe built-in instructions, so instructions
+/// running custom opcodes registered with
+/// [`IntCodeComputer::register_opcode`] are executed without being recorded.
+/// They still count towards the step numbers of later entries, which are taken
+/// from [`IntCodeComputer::steps`].
+pub struct Tracer<S: TraceSink> {
+    computer: IntCodeComputer,
+    sink: S,
+    /// The step and address of the last halt recorded, so that running a
+    /// halted machine again doesn't record the halt again.
+    halted: Option<(u64, usize)>,
+}
+
+impl<S: TraceSink> Tracer<S> {
+    pub fn new(computer: IntCodeComputer, sink: S) -> Self {
+        Tracer {
+            computer,
+            sink,
+            halted: None,
+        }
+    }
+
+    pub fn computer(&self) -> &IntCodeComputer {
+        &self.computer
+    }
+
+    pub fn computer_mut(&mut self) -> &mut IntCodeComputer {
+        &mut self.computer
+    }
+
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    pub fn into_parts(self) -> (IntCodeComputer, S) {
+        (self.computer, self.sink)
+    }
+
+    /// Executes a single instruction like [`IntCodeComputer::step`], recording
+    /// it if it was executed.
+    pub fn step(&mut self) -> Result<Option<Status>, TraceError> {
+        if self.computer.at_custom_opcode() {
+            return Ok(self.computer.step()?);
+        }
+
+        let computer = &self.computer;
+        let step = computer.steps();
+        let ip = computer.ip();
+        let instruction = match Instruction::decode(computer.get(ip)) {
+            Ok(instruction) => instruction,
+            // the machine faults too, saying where
+            Err(_) => return Ok(self.computer.step()?),
+        };
+        let output = instruction.opcode.output_parameter();
+        let parameters: Vec<MemoryValue> = (1..instruction.size())
+            .map(|offset| computer.get(ip + offset))
+            .collect();
+        let reads = (0..parameters.len())
+            .filter(|&index| Some(index) != output)
+            .map(|index| {
+                let addr = computer.parameter_address(instruction.modes[index], index)?;
+                Ok(Access {
+                    addr,
+                    value: computer.get(addr),
+                })
+            })
+            .collect::<Result<_, IntcodeError>>()?;
+        let target = computer.next_write()?;
+        let relative_base = computer.relative_base();
+
+        let status = self.computer.step()?;
+        match status {
+            Some(Status::NeedsInput) => return Ok(status),
+            Some(Status::Halted) if self.halted == Some((step, ip)) => return Ok(status),
+            Some(Status::Halted) => self.halted = Some((step, ip)),
+            _ => {}
+        }
+
+        let entry = TraceEntry {
+            step,
+            ip,
+            instruction,
+            parameters,
+            reads,
+            write: target.map(|addr| Access {
+                addr,
+                value: self.computer.get(addr),
+            }),
+            relative_base,
+            new_relative_base: match instruction.opcode {
+                Opcode::AdjustRelativeBase => Some(self.computer.relative_base()),
+                _ => None,
+            },
+        };
+        self.sink.record(&entry).map_err(TraceError::Sink)?;
+
+        Ok(status)
+    }
+
+    /// Runs the program like [`IntCodeComputer::run`], recording each
+    /// instruction executed.
+    pub fn run(&mut self) -> Result<Status, TraceError> {
+        loop {
+            if let Some(status) = self.step()? {
+                return Ok(status);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assemble, extension::fixtures::subtract, Mode, Program};
+
+    fn trace(source: &str, input: &[MemoryValue]) -> Vec<TraceEntry> {
+        let program = Program::from(assemble(source).unwrap());
+        let mut computer = IntCodeComputer::new(&program);
+        for &value in input {
+            computer.provide_input(value);
+        }
+
+        let mut tracer = Tracer::new(computer, Vec::new());
+        while tracer.run().unwrap() != Status::Halted {}
+        tracer.into_parts().1
+    }
+
+    const PROGRAM: &str = "
+        IN -> [a]
+        ARB #a
+        ADD [rb+0], #5 -> [rb+1]
+        OUT [rb+1]
+        HLT
+        a: DATA 0, 0
+    ";
+
+    #[test]
+    fn entries() {
+        let entries = trace(PROGRAM, &[2]);
+        assert_eq!(entries.len(), 5);
+        assert_eq!(
+            entries[0],
+            TraceEntry {
+                step: 0,
+                ip: 0,
+                instruction: Instruction::new(Opcode::Input, [Mode::Position; 3]),
+                parameters: vec![11],
+                reads: vec![],
+                write: Some(Access { addr: 11, value: 2 }),
+                relative_base: 0,
+                new_relative_base: None,
+            }
+        );
+        assert_eq!(entries[1].new_relative_base, Some(11));
+        assert_eq!(
+            entries[2].reads,
+            [Access { addr: 11, value: 2 }, Access { addr: 6, value: 5 }]
+        );
+        assert_eq!(entries[2].write, Some(Access { addr: 12, value: 7 }));
+        assert_eq!(entries[2].relative_base, 11);
+        assert_eq!(entries[3].reads, [Access { addr: 12, value: 7 }]);
+        assert_eq!(entries[4].instruction.opcode, Opcode::Halt);
+        assert_eq!(entries[4].step, 4);
+    }
+
+    #[test]
+    fn json_lines() {
+        let entries = trace(PROGRAM, &[2]);
+        let mut json = Vec::new();
+        entries[2].write_json(&mut json).unwrap();
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            "{\"step\":2,\"ip\":4,\"opcode\":\"ADD\",\"modes\":[2,1,2],\"parameters\":[0,5,1],\
+             \"reads\":[{\"addr\":11,\"value\":2},{\"addr\":6,\"value\":5}],\
+             \"write\":{\"addr\":12,\"value\":7},\"relative_base\":11,\"new_relative_base\":null}\n"
+        );
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let entries = trace(
+            "
+            ARB #-3
+            MUL #-1234567890123, #-1 -> [rb+1003]
+            OUT [1000]
+            HLT
+            ",
+            &[],
+        );
+        let mut writer = BinaryTraceWriter::new(Vec::new());
+        for entry in &entries {
+            writer.record(entry).unwrap();
+        }
+
+        let bytes = writer.into_inner();
+        assert_eq!(read_binary_trace(&bytes[..]).unwrap(), entries);
+        assert!(read_binary_trace(&bytes[..bytes.len() - 1]).is_err());
+        assert!(read_binary_trace(&b"nope!"[..]).is_err());
+        assert!(read_binary_trace(&b""[..]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn custom_opcodes() {
+        // SUB [a], #2 -> [a], then writes the result
+        let program = Program::new(&[1010, 7, 2, 7, 4, 7, 99, 5]);
+        let mut computer = IntCodeComputer::new(&program);
+        computer.register_opcode(10, subtract());
+
+        let mut tracer = Tracer::new(computer, Vec::new());
+        assert_eq!(tracer.run().unwrap(), Status::Output(3));
+        assert_eq!(tracer.run().unwrap(), Status::Halted);
+        let entries = tracer.into_parts().1;
+        let steps: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.step, entry.ip, entry.instruction.opcode))
+            .collect();
+        assert_eq!(steps, [(1, 4, Opcode::Output), (2, 6, Opcode::Halt)]);
+        assert_eq!(entries[0].reads, [Access { addr: 7, value: 3 }]);
+
+        // binary traces keep the gap left by the custom opcode
+        let mut writer = BinaryTraceWriter::new(Vec::new());
+        for entry in &entries {
+            writer.record(entry).unwrap();
+        }
+        assert_eq!(
+            read_binary_trace(&writer.into_inner()[..]).unwrap(),
+            entries
+        );
+    }
+
+    #[test]
+    fn machine_steps() {
+        let program = Program::new(&[104, 1, 104, 2, 99]);
+        let mut computer = IntCodeComputer::new(&program);
+        assert_eq!(computer.run().unwrap(), Status::Output(1));
+
+        // numbering carries on from the machine, and halts are recorded once
+        let mut tracer = Tracer::new(computer, Vec::new());
+        assert_eq!(tracer.run().unwrap(), Status::Output(2));
+        for _ in 0..3 {
+            assert_eq!(tracer.run().unwrap(), Status::Halted);
+        }
+        let steps: Vec<_> = tracer
+            .sink()
+            .iter()
+            .map(|entry| (entry.step, entry.ip))
+            .collect();
+        assert_eq!(steps, [(1, 2), (2, 4)]);
+        assert_eq!(tracer.computer().steps(), 2);
+    }
+
+    #[test]
+    fn errors() {
+        struct Closed;
+
+        impl TraceSink for Closed {
+            fn record(&mut self, _: &TraceEntry) -> anyhow::Result<()> {
+                anyhow::bail!("closed")
+            }
+        }
+
+        let program = Program::new(&[104, 1, 42]);
+        let error = Tracer::new(IntCodeComputer::new(&program), Closed)
+            .run()
+            .unwrap_err();
+        assert!(matches!(error, TraceError::Sink(_)));
+        assert!(!error.is_fault());
+
+        let mut tracer = Tracer::new(IntCodeComputer::new(&program), Vec::new());
+        assert_eq!(tracer.run().unwrap(), Status::Output(1));
+        match tracer.run().unwrap_err() {
+            TraceError::Machine(error) => assert_eq!((error.ip, error.instruction), (2, 42)),
+            error => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[test]
+    fn divergence() {
+        let a = trace(PROGRAM, &[2]);
+        let b = trace(PROGRAM, &[3]);
+        assert_eq!(first_divergence(&a, &a), None);
+        assert_eq!(first_divergence(&a, &b), Some(0));
+        assert_eq!(first_divergence(&a, &a[..3]), Some(3));
+    }
+}
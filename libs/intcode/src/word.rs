@@ -0,0 +1,157 @@
+use std::{
+    fmt::{Debug, Display},
+    num::Wrapping,
+};
+
+/// A value stored in an Intcode machine's memory.
+///
+/// Arithmetic returns `None` if the result doesn't fit in the word, which the
+/// machine reports as an overflow:
+///
+/// - `i64` faults on overflow.
+/// - `Wrapping<i64>` wraps around on overflow, like most Intcode machines.
+/// - `BigInt` never overflows. It's available with the `bigint` feature.
+pub trait Word: Clone + Ord + Debug + Display + Default + Send + Sync + 'static {
+    fn from_i64(value: i64) -> Self;
+
+    /// Converts the word to an `i64`, if it fits.
+    fn to_i64(&self) -> Option<i64>;
+
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+
+    fn is_zero(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Word for i64 {
+    fn from_i64(value: i64) -> Self {
+        value
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        Some(*self)
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        i64::checked_add(*self, *other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        i64::checked_mul(*self, *other)
+    }
+}
+
+impl Word for Wrapping<i64> {
+    fn from_i64(value: i64) -> Self {
+        Wrapping(value)
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        Some(self.0)
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(*self + *other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        Some(*self * *other)
+    }
+}
+
+#[cfg(feature = "bigint")]
+impl Word for num_bigint::BigInt {
+    fn from_i64(value: i64) -> Self {
+        value.into()
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        std::convert::TryFrom::try_from(self).ok()
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(self + other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        Some(self * other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ErrorKind, IntCodeComputer, IntcodeError, Program, Status};
+
+    /// Multiplies 2^62 by 4 and adds 1 to the result, writing each value.
+    const OVERFLOWING: &str = "
+        MUL #4611686018427387904, #4 -> [value]
+        OUT [value]
+        ADD [value], #1 -> [value]
+        OUT [value]
+        HLT
+        value: DATA 0
+    ";
+
+    fn outputs<W: Word>(source: &str) -> Result<Vec<W>, IntcodeError> {
+        let memory: Vec<W> = crate::assemble(source)
+            .unwrap()
+            .into_iter()
+            .map(W::from_i64)
+            .collect();
+        let mut computer = IntCodeComputer::new(&Program::from(memory));
+        let mut output = Vec::new();
+        loop {
+            match computer.run()? {
+                Status::Output(value) => output.push(value),
+                _ => return Ok(output),
+            }
+        }
+    }
+
+    #[test]
+    fn checked() {
+        let error = outputs::<i64>(OVERFLOWING).unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::Overflow));
+        assert_eq!((error.ip, error.instruction), (0, 1102));
+
+        // day09's large number self-tests still fit
+        assert_eq!(
+            outputs::<i64>("MUL #34915192, #34915192 -> [7]\nOUT [7]\nHLT\nDATA 0").unwrap(),
+            [1219070632396864]
+        );
+    }
+
+    #[test]
+    fn wrapping() {
+        assert_eq!(
+            outputs::<Wrapping<i64>>(OVERFLOWING).unwrap(),
+            [Wrapping(0), Wrapping(1)]
+        );
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn bigint() {
+        use num_bigint::BigInt;
+
+        let big = BigInt::from(1u128 << 64);
+        assert_eq!(
+            outputs::<BigInt>(OVERFLOWING).unwrap(),
+            [big.clone(), big + 1]
+        );
+
+        // values too large to be addresses are faults
+        let error = outputs::<BigInt>(
+            "
+                    MUL #4611686018427387904, #4 -> [output+1]
+            output: OUT [0]
+            ",
+        )
+        .unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::Overflow));
+        assert_eq!((error.ip, error.parameter), (4, Some(0)));
+    }
+}
@@ -1,19 +1,27 @@
-#[path = "../lib/intcode.rs"]
-mod intcode;
-
-use anyhow::Context;
-use intcode::{IntCodeComputer, MemoryValue};
+use anyhow::bail;
+use intcode::{
+    Expr, IntCodeComputer, IntcodeError, Limits, MemoryValue, Program, Status, SymbolicComputer,
+};
 use itertools::Itertools;
 use std::io::BufRead;
 
 const INPUT: &str = include_str!("input.txt");
+const TARGET: MemoryValue = 19690720;
 
-fn run(memory: &mut [MemoryValue]) -> anyhow::Result<()> {
+fn run(
+    program: &Program,
+    noun: MemoryValue,
+    verb: MemoryValue,
+) -> Result<MemoryValue, IntcodeError> {
     let stdin = std::io::stdin();
     let mut istream = stdin.lock();
     let mut buffer = String::with_capacity(64);
-    let mut computer = IntCodeComputer::new(
-        memory,
+    let mut computer = IntCodeComputer::new(program);
+    // the program runs straight through, so anything longer is stuck
+    computer.set_limits(Limits::none().with_max_steps(100_000));
+    computer.set(1, noun);
+    computer.set(2, verb);
+    computer.run_with(
         || match istream.read_line(&mut buffer)? {
             0 => Ok(None),
             _ => Ok(Some(buffer.trim().parse()?)),
@@ -22,43 +30,68 @@ fn run(memory: &mut [MemoryValue]) -> anyhow::Result<()> {
             println!("{}", value);
             Ok(())
         },
-    );
-    computer.run()?;
-    Ok(())
+    )?;
+    Ok(computer.get(0))
 }
 
-fn part1(memory: &mut [MemoryValue]) -> anyhow::Result<MemoryValue> {
-    memory[1] = 12;
-    memory[2] = 2;
-    run(memory)?;
-    Ok(memory[0])
+fn part1(program: &Program) -> anyhow::Result<MemoryValue> {
+    Ok(run(program, 12, 2)?)
+}
+
+/// Finds the noun and verb by solving for them, which only works if the
+/// program's control flow doesn't depend on them.
+fn solve(program: &Program) -> anyhow::Result<Option<(MemoryValue, MemoryValue)>> {
+    // address 0 ends up as an expression of the noun and verb
+    let mut computer = SymbolicComputer::new(&IntCodeComputer::new(program));
+    computer.set(1, Expr::symbol("noun"));
+    computer.set(2, Expr::symbol("verb"));
+    loop {
+        match computer.run()? {
+            Status::Halted => break,
+            Status::Output(_) => {}
+            Status::NeedsInput => bail!("unexpected input instruction"),
+        }
+    }
+
+    let ranges = [("noun", 0..=99), ("verb", 0..=99)];
+    let pair = computer
+        .get(0)
+        .solve(TARGET, &ranges)
+        .map(|values| (values[0], values[1]));
+
+    // the expression can't tell whether the program faults for the pair
+    Ok(pair.filter(|&(noun, verb)| matches!(run(program, noun, verb), Ok(TARGET))))
 }
 
-fn part2(source: &mut [MemoryValue]) -> anyhow::Result<MemoryValue> {
-    let mut memory = vec![0; source.len()];
-    let result = (0..100)
-        .cartesian_product(0..100)
-        .find(|&(noun, verb)| {
-            memory.copy_from_slice(&source);
-            memory[1] = noun;
-            memory[2] = verb;
-            run(&mut memory).is_ok() && memory[0] == 19690720
-        })
-        .map(|(noun, verb)| 100 * noun + verb)
-        .context("no valid pairs")?;
+/// Finds the noun and verb by running the program with every pair.
+fn search(program: &Program) -> Option<(MemoryValue, MemoryValue)> {
+    let mut computer = IntCodeComputer::new(program);
+    computer.set_limits(Limits::none().with_max_steps(100_000));
+    let pairs: Vec<_> = (0..100).cartesian_product(0..100).collect();
+    intcode::search(
+        &computer,
+        &pairs,
+        |machine, &(noun, verb)| {
+            machine.set(1, noun);
+            machine.set(2, verb);
+        },
+        |machine, _| machine.get(0) == TARGET,
+    )
+    .copied()
+}
 
-    Ok(result)
+fn part2(program: &Program) -> anyhow::Result<MemoryValue> {
+    match solve(program).ok().flatten().or_else(|| search(program)) {
+        Some((noun, verb)) => Ok(100 * noun + verb),
+        None => bail!("no valid pairs"),
+    }
 }
 
 fn main() -> anyhow::Result<()> {
-    let mut memory: Vec<MemoryValue> = INPUT
-        .trim_end()
-        .split(',')
-        .map(|s| s.parse())
-        .collect::<Result<_, _>>()?;
+    let program: Program = INPUT.parse()?;
 
-    println!("part 1: {}", part1(&mut memory.clone())?);
-    println!("part 2: {}", part2(&mut memory)?);
+    println!("part 1: {}", part1(&program)?);
+    println!("part 2: {}", part2(&program)?);
 
     Ok(())
 }
@@ -69,14 +102,10 @@ mod tests {
 
     #[test]
     fn it_works() {
-        let mut memory: Vec<MemoryValue> = INPUT
-            .trim_end()
-            .split(',')
-            .map(|s| s.parse())
-            .collect::<Result<_, _>>()
-            .unwrap();
+        let program: Program = INPUT.parse().unwrap();
 
-        assert_eq!(part1(&mut memory.clone()).unwrap(), 3895705);
-        assert_eq!(part2(&mut memory).unwrap(), 6417);
+        assert_eq!(part1(&program).unwrap(), 3895705);
+        assert_eq!(part2(&program).unwrap(), 6417);
+        assert_eq!(search(&program), Some((64, 17)));
     }
 }
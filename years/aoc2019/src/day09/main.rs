@@ -1,35 +1,50 @@
-#[path = "../lib/intcode.rs"]
-mod intcode;
-
-use intcode::{IntCodeComputer, MemoryValue};
+use anyhow::bail;
+use intcode::{IntCodeComputer, MemoryValue, Program, Status};
 
 const INPUT: &str = include_str!("input.txt");
 
-fn run(memory: &mut [MemoryValue], input: &[MemoryValue]) -> anyhow::Result<()> {
-    let mut input_index = 0;
-    let mut computer = IntCodeComputer::new(
-        memory,
-        move || {
-            input_index += 1;
-            Ok(input.get(input_index - 1).copied())
-        },
-        |value| {
-            println!("> {}", value);
-            Ok(())
-        },
-    );
-    computer.run()?;
-    Ok(())
+fn run(program: &Program, input: &[MemoryValue]) -> anyhow::Result<Vec<MemoryValue>> {
+    let mut computer = IntCodeComputer::new(program);
+    computer.set_instruction_cache(true);
+    for &value in input {
+        computer.provide_input(value);
+    }
+
+    let mut output = Vec::new();
+    loop {
+        match computer.run()? {
+            Status::Output(value) => output.push(value),
+            Status::Halted => break Ok(output),
+            Status::NeedsInput => bail!("unexpected end of input"),
+        }
+    }
 }
 
 fn main() -> anyhow::Result<()> {
-    let mut memory: Vec<MemoryValue> = INPUT.trim().split(',').flat_map(|s| s.parse()).collect();
+    let program: Program = INPUT.parse()?;
 
     println!("part 1:");
-    run(&mut memory.clone(), &[1])?;
+    for value in run(&program, &[1])? {
+        println!("> {}", value);
+    }
 
     println!("part 2:");
-    run(&mut memory, &[2])?;
+    for value in run(&program, &[2])? {
+        println!("> {}", value);
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let program: Program = INPUT.parse().unwrap();
+
+        assert_eq!(run(&program, &[1]).unwrap(), [2453265701]);
+        assert_eq!(run(&program, &[2]).unwrap(), [80805]);
+    }
+}
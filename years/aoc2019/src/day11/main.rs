@@ -1,10 +1,7 @@
-#[path = "../lib/intcode.rs"]
-mod intcode;
-
 use anyhow::bail;
-use intcode::{IntCodeComputer, MemoryValue};
+use intcode::{IntCodeComputer, MemoryValue, Program, Status};
 use itertools::{Itertools, MinMaxResult};
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::collections::HashMap;
 
 const INPUT: &str = include_str!("input.txt");
 
@@ -68,71 +65,60 @@ impl Default for State {
     }
 }
 
-fn run(memory: &mut [MemoryValue], state: &mut State) -> anyhow::Result<()> {
-    let state = Rc::new(RefCell::new(state));
-    let mut computer = IntCodeComputer::new(
-        memory,
-        {
-            let state = state.clone();
-            move || {
-                let state = state.borrow();
-                Ok(state
+fn run(program: &Program, state: &mut State) -> anyhow::Result<()> {
+    let mut computer = IntCodeComputer::new(program);
+    loop {
+        match computer.run()? {
+            Status::NeedsInput => {
+                let color = state
                     .painted
                     .get(&state.position)
                     .copied()
-                    .map(MemoryValue::from)
-                    .or(Some(0)))
+                    .unwrap_or(Color::Black);
+                computer.provide_input(color.into());
             }
-        },
-        {
-            let mut color = None;
-            move |value| match color.take() {
-                None => {
-                    color = Some(value);
-                    Ok(())
-                }
-                Some(color) => {
-                    let mut state = state.borrow_mut();
-                    let position = state.position;
-                    let color = match color {
-                        0 => Color::Black,
-                        1 => Color::White,
-                        _ => bail!("unknown color: {}", color),
-                    };
-                    state.painted.insert(position, color);
-
-                    match value {
-                        0 => state.direction = state.direction.rotate_ccw(),
-                        1 => state.direction = state.direction.rotate_cw(),
-                        _ => bail!("unknown direction: {}", value),
-                    }
-                    state.position = match state.direction {
-                        Direction::Up => (state.position.0, state.position.1 - 1),
-                        Direction::Right => (state.position.0 + 1, state.position.1),
-                        Direction::Down => (state.position.0, state.position.1 + 1),
-                        Direction::Left => (state.position.0 - 1, state.position.1),
-                    };
-
-                    Ok(())
+            Status::Output(color) => {
+                let turn = match computer.run()? {
+                    Status::Output(turn) => turn,
+                    status => bail!("expected direction, got {:?}", status),
+                };
+
+                let color = match color {
+                    0 => Color::Black,
+                    1 => Color::White,
+                    _ => bail!("unknown color: {}", color),
+                };
+                state.painted.insert(state.position, color);
+
+                match turn {
+                    0 => state.direction = state.direction.rotate_ccw(),
+                    1 => state.direction = state.direction.rotate_cw(),
+                    _ => bail!("unknown direction: {}", turn),
                 }
+                state.position = match state.direction {
+                    Direction::Up => (state.position.0, state.position.1 - 1),
+                    Direction::Right => (state.position.0 + 1, state.position.1),
+                    Direction::Down => (state.position.0, state.position.1 + 1),
+                    Direction::Left => (state.position.0 - 1, state.position.1),
+                };
             }
-        },
-    );
-    computer.run()?;
+            Status::Halted => break,
+        }
+    }
 
     Ok(())
 }
 
-fn part1(memory: &mut [MemoryValue]) -> anyhow::Result<usize> {
+fn part1(program: &Program) -> anyhow::Result<usize> {
     let mut state = State::default();
-    run(memory, &mut state)?;
+    run(program, &mut state)?;
     Ok(state.painted.len())
 }
 
-fn part2(memory: &mut [MemoryValue]) -> anyhow::Result<()> {
+fn part2(program: &Program) -> anyhow::Result<String> {
     let mut state = State::default();
     state.painted.insert((0, 0), Color::White);
-    run(memory, &mut state)?;
+    run(program, &mut state)?;
 
     let xs = match state.painted.keys().map(|&(x, _)| x).minmax() {
         MinMaxResult::NoElements => bail!("no painted tiles"),
@@ -146,29 +132,50 @@ fn part2(memory: &mut [MemoryValue]) -> anyhow::Result<()> {
         MinMaxResult::MinMax(min, max) => min..=max,
     };
 
+    let mut image = String::new();
     for y in ys {
         for x in xs.clone() {
             let color = state.painted.get(&(x, y)).copied().unwrap_or(Color::Black);
-            print!(
-                "{}",
-                match color {
-                    Color::Black => ".",
-                    Color::White => "#",
-                }
-            );
+            image.push(match color {
+                Color::Black => '.',
+                Color::White => '#',
+            });
         }
 
-        println!();
+        image.push('\n');
     }
 
-    Ok(())
+    Ok(image)
 }
 
 fn main() -> anyhow::Result<()> {
-    let mut memory: Vec<MemoryValue> = INPUT.trim().split(',').flat_map(|s| s.parse()).collect();
-    println!("part 1: {}", part1(&mut memory.clone())?);
+    let program: Program = INPUT.parse()?;
+    println!("part 1: {}", part1(&program)?);
     println!("part 2:");
-    part2(&mut memory)?;
+    print!("{}", part2(&program)?);
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let program: Program = INPUT.parse().unwrap();
+
+        assert_eq!(part1(&program).unwrap(), 2172);
+        assert_eq!(
+            part2(&program).unwrap(),
+            "\
+...##.####.#....####.####..##..#..#.###....
+....#.#....#....#....#....#..#.#..#.#..#...
+....#.###..#....###..###..#....####.#..#...
+....#.#....#....#....#....#.##.#..#.###....
+.#..#.#....#....#....#....#..#.#..#.#......
+..##..####.####.####.#.....###.#..#.#......
+"
+        );
+    }
+}